@@ -0,0 +1,221 @@
+//! Read HTTP/1.1 requests from, and write responses to, a TCP connection.
+//!
+//! Only what Micropub clients send is supported: requests with a `Content-Length`, answered one
+//! per connection.
+
+use serde_json::{Value, json};
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// The largest request head accepted.
+const MAX_HEAD_LENGTH: usize = 64 * 1024;
+/// The largest request body accepted.
+const MAX_BODY_LENGTH: usize = 50 * 1024 * 1024;
+
+/// Represents a request received by the server.
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Get the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the first value of a query string parameter.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the media type of the body, without its parameters, in lowercase.
+    pub fn content_type(&self) -> String {
+        self.header("Content-Type")
+            .and_then(|value| value.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    }
+}
+
+/// Represents a response to be sent by the server.
+pub(crate) struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Create a response without a body.
+    pub fn empty(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Create a response with a JSON body.
+    pub fn json(status: u16, body: &Value) -> Self {
+        Response {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    /// Create a Micropub error response.
+    pub fn error(status: u16, error: &str, description: &str) -> Self {
+        Response::json(
+            status,
+            &json!({ "error": error, "error_description": description }),
+        )
+    }
+
+    /// Add a header to the response.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Read a request from the connection. Returns `None` if the client closed the connection
+/// without sending one.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> io::Result<Option<Request>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_length = loop {
+        if let Some(position) = find(&buffer, b"\r\n\r\n") {
+            break position + 4;
+        }
+        if buffer.len() > MAX_HEAD_LENGTH {
+            return Err(invalid_data("The request head is too long"));
+        }
+        let length = stream.read(&mut chunk).await?;
+        if length == 0 {
+            return if buffer.is_empty() {
+                Ok(None)
+            } else {
+                Err(invalid_data(
+                    "The connection was closed in the request head",
+                ))
+            };
+        }
+        buffer.extend_from_slice(&chunk[..length]);
+    };
+
+    let mut parsed_headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed = httparse::Request::new(&mut parsed_headers);
+    parsed
+        .parse(&buffer[..head_length])
+        .map_err(|e| invalid_data(&e.to_string()))?;
+    let method = parsed.method.unwrap_or_default().to_string();
+    let target = parsed.path.unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = parsed
+        .headers
+        .iter()
+        .map(|header| {
+            (
+                header.name.to_string(),
+                String::from_utf8_lossy(header.value).into_owned(),
+            )
+        })
+        .collect();
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, parse_form(query.as_bytes())),
+        None => (target.as_str(), Vec::new()),
+    };
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .map(|(_, value)| value.trim().parse::<usize>())
+        .transpose()
+        .map_err(|_| invalid_data("The Content-Length header is not a number"))?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_LENGTH {
+        return Err(invalid_data("The request body is too large"));
+    }
+
+    let mut body = buffer.split_off(head_length);
+    while body.len() < content_length {
+        let length = stream.read(&mut chunk).await?;
+        if length == 0 {
+            return Err(invalid_data(
+                "The connection was closed in the request body",
+            ));
+        }
+        body.extend_from_slice(&chunk[..length]);
+    }
+    body.truncate(content_length);
+
+    Ok(Some(Request {
+        method,
+        path: path.to_string(),
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// Write a response to the connection, and ask the client to close it.
+pub(crate) async fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await
+}
+
+/// Decode `application/x-www-form-urlencoded` data into (name, value) pairs.
+pub(crate) fn parse_form(data: &[u8]) -> Vec<(String, String)> {
+    form_urlencoded::parse(data).into_owned().collect()
+}
+
+/// Find the first position of a byte sequence in another.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        _ => "Unknown",
+    }
+}
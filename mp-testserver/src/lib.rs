@@ -0,0 +1,30 @@
+//! # mp-testserver - Micropub Test Server
+//!
+//! A minimal Micropub server that keeps the posts and media files it receives in memory, for
+//! testing Micropub clients without a real site.
+//!
+//! The server accepts form-encoded and JSON requests that create posts, JSON updates, `delete`
+//! and `undelete` actions, media uploads, and `q=config`, `q=source`, `q=syndicate-to`, and
+//! `q=category` queries. It answers new posts with a `Location` header, as the Micropub
+//! specification requires, or with a JSON body, as Micro.blog does (see [`ResponseStyle`]).
+//!
+//! ```no_run
+//! use mp_testserver::TestServer;
+//!
+//! # async fn example() -> std::io::Result<()> {
+//! let server = TestServer::new("secret-token").start().await?;
+//! println!("Micropub endpoint: {}", server.micropub_url());
+//! // ... send requests to the server ...
+//! for post in server.posts() {
+//!     println!("{}: {:?}", post.url, post.text("content"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod http;
+mod server;
+mod store;
+
+pub use crate::server::{ResponseStyle, RunningTestServer, TestServer};
+pub use crate::store::{StoredMedia, StoredPost};
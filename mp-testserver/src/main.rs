@@ -0,0 +1,70 @@
+use clap::{Arg, ArgAction, Command, crate_version, value_parser};
+use mp_testserver::{ResponseStyle, TestServer};
+
+#[tokio::main]
+async fn main() {
+    let matches = Command::new("mp-testserver")
+        .version(crate_version!())
+        .about("Run a local Micropub server that keeps posts in memory, for testing")
+        .arg(
+            Arg::new("port")
+                .long("port")
+                .value_name("PORT")
+                .help("Port to listen on (default: a free port)")
+                .value_parser(value_parser!(u16))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("token")
+                .long("token")
+                .value_name("TOKEN")
+                .help("Access token that requests must be authenticated with")
+                .default_value("test-token"),
+        )
+        .arg(
+            Arg::new("micro-blog")
+                .long("micro-blog")
+                .help("Answer new posts with a JSON body, as Micro.blog does, instead of a Location header")
+                .action(ArgAction::SetTrue),
+        )
+        .get_matches();
+
+    let port = *matches.get_one::<u16>("port").expect("port has a default");
+    let token = matches
+        .get_one::<String>("token")
+        .expect("token has a default");
+    let response_style = if matches.get_flag("micro-blog") {
+        ResponseStyle::MicroBlog
+    } else {
+        ResponseStyle::Location
+    };
+
+    let server = match TestServer::new(token)
+        .with_response_style(response_style)
+        .with_port(port)
+        .start()
+        .await
+    {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Error starting the server:");
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Micropub endpoint: {}", server.micropub_url());
+    println!("Media endpoint:    {}", server.media_url());
+    println!("Access token:      {}", token);
+    println!("Responses:         {}", response_style.as_str());
+    println!("Press Ctrl-C to stop.");
+
+    let _ = tokio::signal::ctrl_c().await;
+    let posts = server.posts();
+    println!();
+    println!(
+        "Received {} post(s) and {} media file(s).",
+        posts.len(),
+        server.media().len()
+    );
+}
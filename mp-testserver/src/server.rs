@@ -0,0 +1,620 @@
+//! Run the test server and answer Micropub requests.
+
+use serde_json::{Map, Value, json};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::http::{Request, Response, find, parse_form, read_request, write_response};
+use crate::store::{Store, StoredMedia, StoredPost, apply_update};
+
+/// How the server answers requests that create posts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseStyle {
+    /// `201 Created` with the URL of the post in the `Location` header, as the Micropub
+    /// specification requires.
+    #[default]
+    Location,
+    /// `202 Accepted` with the `url`, `preview`, and `edit` URLs of the post in a JSON body, and
+    /// no `Location` header, as Micro.blog answers.
+    MicroBlog,
+}
+
+impl ResponseStyle {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResponseStyle::Location => "location",
+            ResponseStyle::MicroBlog => "micro.blog",
+        }
+    }
+}
+
+/// A Micropub server that keeps the posts and media files it receives in memory.
+///
+/// The server listens on the loopback interface. Every request must be authenticated with the
+/// server's token, in an `Authorization: Bearer` header or an `access_token` form parameter.
+pub struct TestServer {
+    token: String,
+    response_style: ResponseStyle,
+    port: u16,
+}
+
+impl TestServer {
+    /// Create a server that accepts the given token, and listens on a free port.
+    pub fn new(token: &str) -> Self {
+        TestServer {
+            token: token.to_string(),
+            response_style: ResponseStyle::default(),
+            port: 0,
+        }
+    }
+
+    pub fn with_response_style(mut self, response_style: ResponseStyle) -> Self {
+        self.response_style = response_style;
+        self
+    }
+
+    /// Listen on the given port instead of a free one.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Start listening, and answer requests in the background until the returned server is
+    /// dropped.
+    pub async fn start(self) -> io::Result<RunningTestServer> {
+        let listener =
+            TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, self.port))).await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(ServerState {
+            token: self.token,
+            response_style: self.response_style,
+            base_url: base_url.clone(),
+            store: Mutex::new(Store::default()),
+        });
+
+        let accepting = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, accepting.clone()));
+            }
+        });
+
+        Ok(RunningTestServer {
+            base_url,
+            state,
+            task,
+        })
+    }
+}
+
+/// A test server that is answering requests. It stops when it is dropped.
+pub struct RunningTestServer {
+    base_url: String,
+    state: Arc<ServerState>,
+    task: JoinHandle<()>,
+}
+
+impl RunningTestServer {
+    /// The URL of the Micropub endpoint.
+    pub fn micropub_url(&self) -> String {
+        format!("{}/micropub", self.base_url)
+    }
+
+    /// The URL of the media endpoint.
+    pub fn media_url(&self) -> String {
+        format!("{}/media", self.base_url)
+    }
+
+    /// The posts created so far, oldest first, including deleted posts.
+    pub fn posts(&self) -> Vec<StoredPost> {
+        self.state.store().posts.clone()
+    }
+
+    /// The media files uploaded so far, oldest first.
+    pub fn media(&self) -> Vec<StoredMedia> {
+        self.state.store().media.clone()
+    }
+}
+
+impl Drop for RunningTestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The settings and store shared by the connections of a server.
+struct ServerState {
+    token: String,
+    response_style: ResponseStyle,
+    base_url: String,
+    store: Mutex<Store>,
+}
+
+impl ServerState {
+    fn store(&self) -> MutexGuard<'_, Store> {
+        // A handler that panicked cannot leave a half-updated store behind.
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Answer the request sent on a connection. Connections that do not send a valid request are
+/// closed without an answer.
+async fn handle_connection(mut stream: TcpStream, state: Arc<ServerState>) {
+    let response = match read_request(&mut stream).await {
+        Ok(Some(request)) => handle_request(&request, &state),
+        Ok(None) => return,
+        Err(e) => Response::error(400, "invalid_request", &e.to_string()),
+    };
+    let _ = write_response(&mut stream, &response).await;
+}
+
+fn handle_request(request: &Request, state: &ServerState) -> Response {
+    let form = if request.content_type() == "application/x-www-form-urlencoded" {
+        parse_form(&request.body)
+    } else {
+        Vec::new()
+    };
+    if !is_authorized(request, &form, &state.token) {
+        return Response::error(401, "unauthorized", "A valid access token is required");
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/micropub") => handle_query(request, state),
+        ("POST", "/micropub") => match request.content_type().as_str() {
+            "application/x-www-form-urlencoded" => handle_form(form, state),
+            "application/json" => match serde_json::from_slice::<Value>(&request.body) {
+                Ok(Value::Object(body)) => handle_json(body, state),
+                _ => Response::error(400, "invalid_request", "The body is not a JSON object"),
+            },
+            _ => Response::error(
+                415,
+                "invalid_request",
+                "Requests must be form-encoded or JSON",
+            ),
+        },
+        ("POST", "/media") => handle_media_upload(request, state),
+        (_, "/micropub" | "/media") => Response::empty(405),
+        _ => Response::empty(404),
+    }
+}
+
+/// Check the token given in the `Authorization` header or the `access_token` parameter.
+fn is_authorized(request: &Request, form: &[(String, String)], token: &str) -> bool {
+    let header_token = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let form_token = form
+        .iter()
+        .find(|(name, _)| name == "access_token")
+        .map(|(_, value)| value.as_str());
+    header_token.or(form_token) == Some(token)
+}
+
+/// Answer a `q=...` query.
+fn handle_query(request: &Request, state: &ServerState) -> Response {
+    match request.query_param("q") {
+        Some("config") => Response::json(
+            200,
+            &json!({
+                "media-endpoint": format!("{}/media", state.base_url),
+                "syndicate-to": [],
+                "post-types": [
+                    { "type": "note", "name": "Note" },
+                    { "type": "article", "name": "Article" },
+                    { "type": "photo", "name": "Photo" }
+                ],
+                "q": ["config", "source", "syndicate-to", "category"]
+            }),
+        ),
+        Some("syndicate-to") => Response::json(200, &json!({ "syndicate-to": [] })),
+        Some("category") => {
+            let search = request.query_param("search").unwrap_or_default();
+            let mut categories: Vec<String> = Vec::new();
+            let store = state.store();
+            for post in store.posts.iter().filter(|post| !post.deleted) {
+                for category in post.values("category").iter().filter_map(|v| v.as_str()) {
+                    if category.contains(search) && !categories.iter().any(|c| c == category) {
+                        categories.push(category.to_string());
+                    }
+                }
+            }
+            Response::json(200, &json!({ "categories": categories }))
+        }
+        Some("source") => handle_source_query(request, state),
+        Some(query) => Response::error(
+            400,
+            "invalid_request",
+            &format!("The '{}' query is not supported", query),
+        ),
+        None => Response::error(400, "invalid_request", "The 'q' parameter is missing"),
+    }
+}
+
+/// Answer a `q=source` query with the properties of a post, optionally limited to those given
+/// in `properties[]` parameters.
+fn handle_source_query(request: &Request, state: &ServerState) -> Response {
+    let Some(url) = request.query_param("url") else {
+        return Response::error(400, "invalid_request", "The 'url' parameter is missing");
+    };
+    let store = state.store();
+    let Some(post) = store.live_post(url) else {
+        return Response::error(400, "invalid_request", "The post does not exist");
+    };
+
+    let wanted: Vec<&str> = request
+        .query
+        .iter()
+        .filter(|(name, _)| name == "properties[]" || name == "properties")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    if wanted.is_empty() {
+        return Response::json(
+            200,
+            &json!({ "type": ["h-entry"], "properties": post.properties }),
+        );
+    }
+    let properties: Map<String, Value> = post
+        .properties
+        .iter()
+        .filter(|(name, _)| wanted.contains(&name.as_str()))
+        .map(|(name, values)| (name.clone(), values.clone()))
+        .collect();
+    Response::json(200, &json!({ "properties": properties }))
+}
+
+/// Handle a form-encoded request: an action on an existing post, or a new post.
+fn handle_form(form: Vec<(String, String)>, state: &ServerState) -> Response {
+    let param = |wanted: &str| {
+        form.iter()
+            .find(|(name, _)| name == wanted)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if let Some(action) = param("action") {
+        return handle_action(action, param("url"), state);
+    }
+    if param("h") != Some("entry") {
+        return Response::error(400, "invalid_request", "Only h=entry posts can be created");
+    }
+
+    let mut properties = Map::new();
+    for (name, value) in &form {
+        if matches!(name.as_str(), "h" | "access_token") {
+            continue;
+        }
+        let name = name.strip_suffix("[]").unwrap_or(name);
+        let values = properties
+            .entry(name.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(values) = values {
+            values.push(Value::String(value.clone()));
+        }
+    }
+    create_post(properties, true, state)
+}
+
+/// Handle a JSON request: an action on an existing post, or a new post.
+fn handle_json(body: Map<String, Value>, state: &ServerState) -> Response {
+    if let Some(action) = body.get("action") {
+        let Some(action) = action.as_str() else {
+            return Response::error(400, "invalid_request", "'action' must be a string");
+        };
+        let url = body.get("url").and_then(Value::as_str);
+        if action == "update" {
+            return handle_update(url, &body, state);
+        }
+        return handle_action(action, url, state);
+    }
+
+    let is_entry = body
+        .get("type")
+        .and_then(Value::as_array)
+        .is_some_and(|types| types.iter().any(|t| t == "h-entry"));
+    if !is_entry {
+        return Response::error(400, "invalid_request", "Only h-entry posts can be created");
+    }
+    match body.get("properties") {
+        Some(Value::Object(properties)) => create_post(properties.clone(), false, state),
+        _ => Response::error(400, "invalid_request", "'properties' must be an object"),
+    }
+}
+
+/// Store a new post and answer with its URL in the configured style.
+fn create_post(
+    properties: Map<String, Value>,
+    form_encoded: bool,
+    state: &ServerState,
+) -> Response {
+    let mut store = state.store();
+    let id = store.posts.len() + 1;
+    let url = format!("{}/posts/{}", state.base_url, id);
+    store.posts.push(StoredPost {
+        url: url.clone(),
+        properties,
+        form_encoded,
+        deleted: false,
+    });
+
+    match state.response_style {
+        ResponseStyle::Location => Response::empty(201).with_header("Location", &url),
+        ResponseStyle::MicroBlog => Response::json(
+            202,
+            &json!({
+                "url": url,
+                "preview": format!("{}/preview/{}", state.base_url, id),
+                "edit": format!("{}/edit/{}", state.base_url, id)
+            }),
+        ),
+    }
+}
+
+/// Handle a JSON update request.
+fn handle_update(url: Option<&str>, body: &Map<String, Value>, state: &ServerState) -> Response {
+    let Some(url) = url else {
+        return Response::error(400, "invalid_request", "The 'url' parameter is missing");
+    };
+    let mut store = state.store();
+    let Some(post) = store.post_mut(url).filter(|post| !post.deleted) else {
+        return Response::error(400, "invalid_request", "The post does not exist");
+    };
+    match apply_update(&mut post.properties, body) {
+        Ok(()) => Response::empty(204),
+        Err(description) => Response::error(400, "invalid_request", &description),
+    }
+}
+
+/// Handle a `delete` or `undelete` action.
+fn handle_action(action: &str, url: Option<&str>, state: &ServerState) -> Response {
+    let deleted = match action {
+        "delete" => true,
+        "undelete" => false,
+        "update" => {
+            return Response::error(
+                400,
+                "invalid_request",
+                "Updates must be sent as JSON requests",
+            );
+        }
+        _ => {
+            return Response::error(
+                400,
+                "invalid_request",
+                &format!("The '{}' action is not supported", action),
+            );
+        }
+    };
+    let Some(url) = url else {
+        return Response::error(400, "invalid_request", "The 'url' parameter is missing");
+    };
+    match state.store().post_mut(url) {
+        Some(post) => {
+            post.deleted = deleted;
+            Response::empty(204)
+        }
+        None => Response::error(400, "invalid_request", "The post does not exist"),
+    }
+}
+
+/// Store the file sent in the `file` part of a multipart request.
+fn handle_media_upload(request: &Request, state: &ServerState) -> Response {
+    if request.content_type() != "multipart/form-data" {
+        return Response::error(
+            400,
+            "invalid_request",
+            "Media must be sent as multipart data",
+        );
+    }
+    let boundary = request
+        .header("Content-Type")
+        .and_then(|value| {
+            value
+                .split(';')
+                .find_map(|param| param.trim().strip_prefix("boundary="))
+        })
+        .map(|boundary| boundary.trim_matches('"').to_string());
+    let Some(boundary) = boundary else {
+        return Response::error(400, "invalid_request", "The multipart boundary is missing");
+    };
+    let Some(file) = find_file_part(&request.body, &boundary) else {
+        return Response::error(400, "invalid_request", "The 'file' part is missing");
+    };
+
+    let mut store = state.store();
+    let extension = file
+        .file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| format!(".{}", extension))
+        .unwrap_or_default();
+    let url = format!(
+        "{}/media/{}{}",
+        state.base_url,
+        store.media.len() + 1,
+        extension
+    );
+    store.media.push(StoredMedia {
+        url: url.clone(),
+        ..file
+    });
+    Response::empty(201).with_header("Location", &url)
+}
+
+/// Find the part named `file` in a multipart body.
+fn find_file_part(body: &[u8], boundary: &str) -> Option<StoredMedia> {
+    let delimiter = format!("--{}", boundary);
+    let mut rest = &body[find(body, delimiter.as_bytes())? + delimiter.len()..];
+
+    // Each part starts after a delimiter and ends with CRLF before the next one.
+    while let Some(end) = find(rest, format!("\r\n{}", delimiter).as_bytes()) {
+        let part = rest[..end].strip_prefix(b"\r\n").unwrap_or(&rest[..end]);
+        rest = &rest[end + 2 + delimiter.len()..];
+
+        let head_end = find(part, b"\r\n\r\n")?;
+        let head = String::from_utf8_lossy(&part[..head_end]);
+        let mut name = None;
+        let mut file_name = String::new();
+        let mut content_type = "application/octet-stream".to_string();
+        for line in head.lines() {
+            let Some((header, value)) = line.split_once(':') else {
+                continue;
+            };
+            if header.eq_ignore_ascii_case("Content-Disposition") {
+                for param in value.split(';').map(str::trim) {
+                    if let Some(value) = param.strip_prefix("name=") {
+                        name = Some(value.trim_matches('"').to_string());
+                    } else if let Some(value) = param.strip_prefix("filename=") {
+                        file_name = value.trim_matches('"').to_string();
+                    }
+                }
+            } else if header.eq_ignore_ascii_case("Content-Type") {
+                content_type = value.trim().to_string();
+            }
+        }
+
+        if name.as_deref() == Some("file") {
+            return Some(StoredMedia {
+                url: String::new(),
+                file_name,
+                content_type,
+                bytes: part[head_end + 4..].to_vec(),
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mplib::{
+        MicropubClient, MicropubService, Post, PostError, PostStatus, PostUpdate, RequestFormat,
+    };
+
+    fn client(server: &RunningTestServer, token: &str, format: RequestFormat) -> MicropubClient {
+        let service = MicropubService::new(server.micropub_url(), token.to_string())
+            .with_request_format(format);
+        MicropubClient::new(service).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_form_post_update_source_and_delete() {
+        let server = TestServer::new("token").start().await.unwrap();
+        let client = client(&server, "token", RequestFormat::Form);
+
+        let mut post = Post::from_body("Hello".to_string(), PostStatus::Published);
+        post.categories = vec!["rust".to_string(), "cli".to_string()];
+        let url = client.create(post).await.unwrap().url;
+        assert_eq!(url, format!("{}/posts/1", server.base_url));
+
+        let mut update = PostUpdate::new(url.clone());
+        update.replace.push_text("content", "Hello again");
+        client.update(update).await.unwrap();
+
+        let source: Value = client
+            .query(
+                "source",
+                &[("url", url.as_str()), ("properties[]", "content")],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            source,
+            json!({ "properties": { "content": ["Hello again"] } })
+        );
+        let categories = client.query_categories(Some("r")).await.unwrap();
+        assert_eq!(categories, vec!["rust"]);
+
+        client.delete(&url).await.unwrap();
+        assert!(server.posts()[0].deleted);
+        assert!(client.query_categories(None).await.unwrap().is_empty());
+        client.undelete(&url).await.unwrap();
+        assert!(!server.posts()[0].deleted);
+        assert!(server.posts()[0].form_encoded);
+    }
+
+    #[tokio::test]
+    async fn test_json_post_with_micro_blog_responses() {
+        let server = TestServer::new("token")
+            .with_response_style(ResponseStyle::MicroBlog)
+            .start()
+            .await
+            .unwrap();
+        let client = client(&server, "token", RequestFormat::Json);
+
+        let post =
+            Post::from_body_and_title("Body".to_string(), "Title".to_string(), PostStatus::Draft);
+        let result = client.create(post).await.unwrap();
+        assert_eq!(result.url, format!("{}/posts/1", server.base_url));
+        assert_eq!(
+            result.preview,
+            Some(format!("{}/preview/1", server.base_url))
+        );
+
+        let posts = server.posts();
+        assert!(!posts[0].form_encoded);
+        assert_eq!(posts[0].text("name"), Some("Title"));
+        assert_eq!(posts[0].text("post-status"), Some("draft"));
+    }
+
+    #[tokio::test]
+    async fn test_media_upload_and_config_query() {
+        let server = TestServer::new("token").start().await.unwrap();
+        let client = client(&server, "token", RequestFormat::Form);
+
+        let config = client.query_config().await.unwrap();
+        let media_endpoint = config.media_endpoint.unwrap();
+        assert_eq!(media_endpoint, server.media_url());
+
+        let path = std::env::temp_dir().join(format!("mp-testserver-{}.txt", std::process::id()));
+        std::fs::write(&path, "file contents").unwrap();
+        let url = client.upload_media(&path, &media_endpoint).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(url, format!("{}/media/1.txt", server.base_url));
+        let media = server.media();
+        assert_eq!(media[0].content_type, "text/plain");
+        assert_eq!(media[0].bytes, b"file contents");
+    }
+
+    #[tokio::test]
+    async fn test_requests_with_wrong_token_are_rejected() {
+        let server = TestServer::new("token").start().await.unwrap();
+        let client = client(&server, "wrong", RequestFormat::Form);
+
+        let post = Post::from_body("Hello".to_string(), PostStatus::Published);
+        let result = client.create(post).await;
+        assert!(matches!(result, Err(PostError::Unauthorized(_))));
+        assert!(server.posts().is_empty());
+    }
+
+    #[test]
+    fn test_blocking_client_post_update_and_delete() {
+        // The server runs on worker threads, while the blocking client waits on this one.
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(TestServer::new("token").start()).unwrap();
+        let service = MicropubService::new(server.micropub_url(), "token".to_string());
+        let client = mplib::blocking::MicropubClient::new(service).unwrap();
+
+        let post = Post::from_body("Hello".to_string(), PostStatus::Published);
+        let url = client.create(post).unwrap().url;
+        let mut update = PostUpdate::new(url.clone());
+        update.add.push_text("category", "rust");
+        client.update(update).unwrap();
+        assert_eq!(client.query_categories(None).unwrap(), vec!["rust"]);
+
+        client.delete(&url).unwrap();
+        assert!(server.posts()[0].deleted);
+    }
+
+    #[test]
+    fn test_find_file_part() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"other\"\r\n\r\nignored\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"cat.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n\xff\xd8\r\nend\r\n--XyZ--\r\n";
+        let file = find_file_part(body, "XyZ").unwrap();
+        assert_eq!(file.file_name, "cat.jpg");
+        assert_eq!(file.content_type, "image/jpeg");
+        assert_eq!(file.bytes, b"\xff\xd8\r\nend");
+        assert!(find_file_part(body, "Other").is_none());
+    }
+}
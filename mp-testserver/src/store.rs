@@ -0,0 +1,170 @@
+//! Keep the posts and media files received by the server in memory.
+
+use serde_json::{Map, Value};
+
+/// Represents a post created on the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredPost {
+    pub url: String,
+    /// The microformats2 properties of the post: each property name maps to an array of values.
+    /// Commands such as `mp-slug` are kept as they were sent.
+    pub properties: Map<String, Value>,
+    /// Whether the request that created the post was form-encoded rather than JSON.
+    pub form_encoded: bool,
+    pub deleted: bool,
+}
+
+impl StoredPost {
+    /// Get the values of a property.
+    pub fn values(&self, name: &str) -> Vec<&Value> {
+        match self.properties.get(name) {
+            Some(Value::Array(values)) => values.iter().collect(),
+            Some(value) => vec![value],
+            None => Vec::new(),
+        }
+    }
+
+    /// Get the first value of a property if it is text.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.values(name).first()?.as_str()
+    }
+}
+
+/// Represents a file uploaded to the media endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredMedia {
+    pub url: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The posts and media files received by the server.
+#[derive(Debug, Default)]
+pub(crate) struct Store {
+    pub posts: Vec<StoredPost>,
+    pub media: Vec<StoredMedia>,
+}
+
+impl Store {
+    /// Find a post by its URL, including deleted posts.
+    pub fn post_mut(&mut self, url: &str) -> Option<&mut StoredPost> {
+        self.posts.iter_mut().find(|post| post.url == url)
+    }
+
+    /// Find a post that was not deleted by its URL.
+    pub fn live_post(&self, url: &str) -> Option<&StoredPost> {
+        self.posts
+            .iter()
+            .find(|post| post.url == url && !post.deleted)
+    }
+}
+
+/// Apply the `replace`, `add`, and `delete` operations of a JSON update request to the
+/// properties of a post.
+pub(crate) fn apply_update(
+    properties: &mut Map<String, Value>,
+    update: &Map<String, Value>,
+) -> Result<(), String> {
+    if let Some(replace) = update.get("replace") {
+        for (name, values) in as_object(replace, "replace")? {
+            properties.insert(name.clone(), Value::Array(as_array(values, name)?.clone()));
+        }
+    }
+
+    if let Some(add) = update.get("add") {
+        for (name, values) in as_object(add, "add")? {
+            let added = as_array(values, name)?;
+            match properties.get_mut(name) {
+                Some(Value::Array(existing)) => existing.extend(added.iter().cloned()),
+                _ => {
+                    properties.insert(name.clone(), Value::Array(added.clone()));
+                }
+            }
+        }
+    }
+
+    match update.get("delete") {
+        None => {}
+        // A list of property names removes the properties entirely.
+        Some(Value::Array(names)) => {
+            for name in names {
+                let name = name
+                    .as_str()
+                    .ok_or("The names of deleted properties must be strings")?;
+                properties.remove(name);
+            }
+        }
+        // An object removes individual values from properties.
+        Some(Value::Object(deleted)) => {
+            for (name, values) in deleted {
+                let removed = as_array(values, name)?;
+                if let Some(Value::Array(existing)) = properties.get_mut(name) {
+                    existing.retain(|value| !removed.contains(value));
+                    if existing.is_empty() {
+                        properties.remove(name);
+                    }
+                }
+            }
+        }
+        Some(_) => return Err("'delete' must be an array or an object".to_string()),
+    }
+
+    Ok(())
+}
+
+fn as_object<'a>(value: &'a Value, operation: &str) -> Result<&'a Map<String, Value>, String> {
+    value
+        .as_object()
+        .ok_or_else(|| format!("'{}' must be an object", operation))
+}
+
+fn as_array<'a>(value: &'a Value, property: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("The values of '{}' must be an array", property))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("expected a JSON object"),
+        }
+    }
+
+    #[test]
+    fn test_apply_update_replace_add_and_delete_values() {
+        let mut properties =
+            object(json!({ "name": ["Old"], "category": ["rust", "typo"], "content": ["Body"] }));
+        let update = object(json!({
+            "replace": { "name": ["New"] },
+            "add": { "category": ["cli"], "syndication": ["https://example.social/1"] },
+            "delete": { "category": ["typo"], "content": ["Body"] }
+        }));
+
+        apply_update(&mut properties, &update).unwrap();
+        assert_eq!(
+            Value::Object(properties),
+            json!({
+                "name": ["New"],
+                "category": ["rust", "cli"],
+                "syndication": ["https://example.social/1"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_apply_update_delete_properties() {
+        let mut properties = object(json!({ "name": ["Title"], "content": ["Body"] }));
+        apply_update(&mut properties, &object(json!({ "delete": ["name"] }))).unwrap();
+        assert_eq!(Value::Object(properties), json!({ "content": ["Body"] }));
+
+        let invalid = object(json!({ "replace": { "name": "Not an array" } }));
+        assert!(apply_update(&mut Map::new(), &invalid).is_err());
+    }
+}
@@ -2,15 +2,34 @@
 //!
 //! This module handles all CLI argument parsing and command execution.
 
+mod categories_subcommand;
+mod config_subcommand;
 mod configure_subcommand;
+mod delete_and_undelete_subcommands;
+mod edit_subcommand;
+mod editor;
+mod front_matter;
+mod login_subcommand;
+mod media_subcommand;
 mod post_and_draft_subcommands;
+mod queue_subcommand;
+mod server_targets;
+mod user_input;
+mod whoami_subcommand;
 
-use clap::{Command, arg, command, value_parser};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, arg, command, value_parser};
+use mplib::PostStatus;
+
+use crate::cli::delete_and_undelete_subcommands::DeleteAction;
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::overrides::{Origin, Overrides, Setting};
 
 /// Main entry point for the CLI application.
 /// Parses command-line arguments and executes the appropriate command.
 pub async fn run() {
     let matches = command!()
+        .args(global_args())
         .subcommand(
             Command::new("post")
                 .about("Publish a post")
@@ -21,19 +40,177 @@ pub async fn run() {
                 .about("Create a draft")
                 .args(common_content_args()),
         )
+        .subcommand(
+            Command::new("edit")
+                .about("Update a published post")
+                .args(edit_args()),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Delete a post")
+                .args(delete_and_undelete_args()),
+        )
+        .subcommand(
+            Command::new("undelete")
+                .about("Restore a deleted post")
+                .args(delete_and_undelete_args()),
+        )
+        .subcommand(
+            Command::new("categories")
+                .about("List the categories used on existing posts")
+                .arg(
+                    arg!(--search <TERM> "Only list categories matching TERM")
+                        .value_parser(value_parser!(String)),
+                ),
+        )
+        .subcommand(
+            Command::new("media")
+                .about("Manage files on the media endpoint")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("upload")
+                        .about("Upload a file to the media endpoint and print its URL")
+                        .arg(
+                            arg!(<path> "The path of the file to upload")
+                                .value_parser(value_parser!(String)),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("queue")
+                .about("Manage the posts waiting in the outbox")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List the queued posts"))
+                .subcommand(
+                    Command::new("flush")
+                        .about("Publish the queued posts")
+                        .arg(
+                            arg!(--"retry-uncertain" "Also send the posts that may already have been published")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Show a queued post")
+                        .arg(arg!(<id> "The ID of the queued post").value_parser(value_parser!(u32))),
+                )
+                .subcommand(
+                    Command::new("drop")
+                        .about("Remove a post from the outbox without publishing it")
+                        .arg(arg!(<id> "The ID of the queued post").value_parser(value_parser!(u32))),
+                ),
+        )
+        .subcommand(
+            Command::new("login")
+                .about("Log in to your site with IndieAuth and save the resulting profile")
+                .arg(
+                    arg!(<homepage> "The URL of your site, such as https://example.com/")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    arg!(--"show-token" "Print the new token if the profile reads its token from a command or environment variable")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("whoami")
+                .about("Verify the authentication token and show who it belongs to"),
+        )
         .subcommand(
             Command::new("configure")
-                .about("Create a configuration file containing your authentication token"),
+                .about("Create or update a profile containing your authentication token")
+                .args(configure_args()),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Inspect the configuration")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("show")
+                        .about("Show the configuration file, or the effective settings")
+                        .arg(
+                            arg!(--effective "Show the settings in effect and where each comes from")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(Command::new("path").about("Print the path of the configuration file"))
+                .subcommand(
+                    Command::new("migrate")
+                        .about("Upgrade the configuration file to the current layout")
+                        .arg(
+                            arg!(--"dry-run" "Show the changes without saving them")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    Command::new("get")
+                        .about("Print the value of a setting")
+                        .arg(arg!(<key> "The setting, such as service.api_url").value_parser(value_parser!(String))),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Change the value of a setting")
+                        .arg(arg!(<key> "The setting, such as service.api_url").value_parser(value_parser!(String)))
+                        .arg(arg!(<value> "The new value").value_parser(value_parser!(String))),
+                )
+                .subcommand(
+                    Command::new("unset")
+                        .about("Remove a setting, or reset it to its default value")
+                        .arg(arg!(<key> "The setting, such as default_behavior.destination").value_parser(value_parser!(String))),
+                )
+                .subcommand(
+                    Command::new("server")
+                        .about("Show the capabilities reported by the Micropub server")
+                        .args(config_server_args()),
+                ),
         )
         .get_matches();
 
+    if let Some(config_path) = setting_from_args(&matches, "config", "MP_CONFIG") {
+        AppConfig::set_config_file_path(config_path);
+    }
+
     // Dispatch to the appropriate subcommand handler
     if let Some(post_matches) = matches.subcommand_matches("post") {
-        post_and_draft_subcommands::handle_post_or_draft_subcommand(post_matches).await;
+        post_and_draft_subcommands::handle_post_or_draft_subcommand(
+            post_matches,
+            PostStatus::Published,
+        )
+        .await;
     } else if let Some(draft_matches) = matches.subcommand_matches("draft") {
-        post_and_draft_subcommands::handle_post_or_draft_subcommand(draft_matches).await;
-    } else if matches.subcommand_matches("configure").is_some() {
-        configure_subcommand::handle_configure_subcommand();
+        post_and_draft_subcommands::handle_post_or_draft_subcommand(
+            draft_matches,
+            PostStatus::Draft,
+        )
+        .await;
+    } else if let Some(edit_matches) = matches.subcommand_matches("edit") {
+        edit_subcommand::handle_edit_subcommand(edit_matches).await;
+    } else if let Some(delete_matches) = matches.subcommand_matches("delete") {
+        delete_and_undelete_subcommands::handle_delete_or_undelete_subcommand(
+            DeleteAction::Delete,
+            delete_matches,
+        )
+        .await;
+    } else if let Some(undelete_matches) = matches.subcommand_matches("undelete") {
+        delete_and_undelete_subcommands::handle_delete_or_undelete_subcommand(
+            DeleteAction::Undelete,
+            undelete_matches,
+        )
+        .await;
+    } else if let Some(categories_matches) = matches.subcommand_matches("categories") {
+        categories_subcommand::handle_categories_subcommand(categories_matches).await;
+    } else if let Some(media_matches) = matches.subcommand_matches("media") {
+        media_subcommand::handle_media_subcommand(media_matches).await;
+    } else if let Some(queue_matches) = matches.subcommand_matches("queue") {
+        queue_subcommand::handle_queue_subcommand(queue_matches).await;
+    } else if let Some(login_matches) = matches.subcommand_matches("login") {
+        login_subcommand::handle_login_subcommand(login_matches).await;
+    } else if let Some(whoami_matches) = matches.subcommand_matches("whoami") {
+        whoami_subcommand::handle_whoami_subcommand(whoami_matches).await;
+    } else if let Some(configure_matches) = matches.subcommand_matches("configure") {
+        configure_subcommand::handle_configure_subcommand(configure_matches).await;
+    } else if let Some(config_matches) = matches.subcommand_matches("config") {
+        config_subcommand::handle_config_subcommand(config_matches).await;
     } else {
         eprintln!("Error:");
         eprintln!("No subcommand was used.\n");
@@ -42,6 +219,59 @@ pub async fn run() {
     }
 }
 
+/// Creates global arguments that override the configuration file. Each can also be given as an
+/// environment variable.
+fn global_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(--profile <NAME> "Use the named profile from the configuration file")
+            .value_parser(value_parser!(String))
+            .env("MP_PROFILE")
+            .global(true),
+        arg!(--config <PATH> "Use the configuration file at PATH")
+            .value_parser(value_parser!(String))
+            .env("MP_CONFIG")
+            .global(true),
+        arg!(--"api-url" <URL> "Use this Micropub endpoint instead of the profile's")
+            .value_parser(value_parser!(String))
+            .env("MP_API_URL")
+            .global(true),
+        arg!(--token <TOKEN> "Use this authentication token instead of the profile's")
+            .value_parser(value_parser!(String))
+            .env("MP_AUTH_TOKEN")
+            .hide_env_values(true)
+            .global(true),
+        arg!(--timeout <SECONDS> "Wait at most SECONDS for each request to the server")
+            .value_parser(value_parser!(u64).range(1..))
+            .env("MP_TIMEOUT")
+            .global(true),
+        arg!(--retries <COUNT> "Retry failed requests up to COUNT times, when it is safe to send them again")
+            .value_parser(value_parser!(u32))
+            .env("MP_RETRIES")
+            .global(true),
+    ]
+}
+
+/// Get the settings given as global arguments or environment variables.
+pub(crate) fn overrides_from_args(matches: &ArgMatches) -> Overrides {
+    Overrides {
+        profile: setting_from_args(matches, "profile", "MP_PROFILE"),
+        api_url: setting_from_args(matches, "api-url", "MP_API_URL"),
+        token: setting_from_args(matches, "token", "MP_AUTH_TOKEN"),
+        timeout: matches.get_one::<u64>("timeout").copied(),
+        retries: matches.get_one::<u32>("retries").copied(),
+    }
+}
+
+/// Get the value of a global argument together with its origin.
+fn setting_from_args(matches: &ArgMatches, id: &str, env_var: &str) -> Option<Setting> {
+    let value = matches.get_one::<String>(id)?.clone();
+    let origin = match matches.value_source(id) {
+        Some(ValueSource::EnvVariable) => Origin::Environment(env_var.to_string()),
+        _ => Origin::Flag(format!("--{}", id)),
+    };
+    Some(Setting { value, origin })
+}
+
 /// Creates common arguments shared by post and draft commands.
 fn common_content_args() -> Vec<clap::Arg> {
     vec![
@@ -53,9 +283,124 @@ fn common_content_args() -> Vec<clap::Arg> {
             .value_parser(value_parser!(String)),
         arg!(-s --stdin "Read content from stdin")
             .action(clap::ArgAction::SetTrue),
+        arg!(--edit "Compose the post in your text editor ($VISUAL or $EDITOR)")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all(["content", "file", "stdin"]),
+        arg!(-c --category <CATEGORY> "Add a category (tag) to the post (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--"syndicate-to" <TARGET> "Syndicate the post to a target, given by UID or name (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--"no-syndicate" "Do not syndicate the post to the default syndication targets")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("syndicate-to"),
+        arg!(--destination <DESTINATION> "Publish to a specific blog of your account, given by UID or name")
+            .value_parser(value_parser!(String)),
+        arg!(--photo <PHOTO> "Attach a photo from a local path or URL, with optional alt text as PATH:ALT (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--video <VIDEO> "Attach a video from a local path or URL (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--audio <AUDIO> "Attach an audio file from a local path or URL (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--html "Treat the content as HTML (requires the JSON request format)")
+            .action(clap::ArgAction::SetTrue),
         arg!(-e --"extract-title" "Extract title from content if it starts with a markdown level 1 header")
             .action(clap::ArgAction::SetTrue),
+        arg!(--queue "Save the post in the outbox instead of publishing it, to publish later with 'mp queue flush'")
+            .action(clap::ArgAction::SetTrue),
+        arg!(-q --quiet "Suppress output")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+/// Creates arguments for the edit command.
+fn edit_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(<url> "The URL of the post to update")
+            .value_parser(value_parser!(String)),
+        arg!(--content <CONTENT> "Replace the content of the post")
+            .value_parser(value_parser!(String))
+            .conflicts_with_all(["file", "stdin"]),
+        arg!(-f --file <PATH> "Replace the content of the post with the content of the file at PATH")
+            .value_parser(value_parser!(String))
+            .conflicts_with("stdin"),
+        arg!(-s --stdin "Replace the content of the post with content read from stdin")
+            .action(clap::ArgAction::SetTrue),
+        arg!(-t --title <TITLE> "Replace the title of the post")
+            .value_parser(value_parser!(String)),
+        arg!(-c --category <CATEGORY> "Replace the categories of the post (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--"add-category" <CATEGORY> "Add a category to the post (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--"remove-category" <CATEGORY> "Remove a category from the post (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--replace <ASSIGNMENT> "Replace a property's values, as PROPERTY=VALUE (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--add <ASSIGNMENT> "Add a value to a property, as PROPERTY=VALUE (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--remove <ASSIGNMENT> "Remove a value from a property, as PROPERTY=VALUE (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
+        arg!(--delete <PROPERTY> "Remove a property entirely (repeatable)")
+            .value_parser(value_parser!(String))
+            .action(clap::ArgAction::Append),
         arg!(-q --quiet "Suppress output")
             .action(clap::ArgAction::SetTrue),
     ]
 }
+
+/// Creates arguments shared by delete and undelete commands.
+fn delete_and_undelete_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(<url> "The URL of the post").value_parser(value_parser!(String)),
+        arg!(-y --yes "Do not ask for confirmation").action(clap::ArgAction::SetTrue),
+        arg!(-q --quiet "Suppress output").action(clap::ArgAction::SetTrue),
+    ]
+}
+
+/// Creates arguments for the configure command. The API URL and token are given with the
+/// global `--api-url` and `--token` arguments.
+fn configure_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(--"request-format" <FORMAT> "Send requests as form-encoded data or JSON")
+            .value_parser(["form", "json"]),
+        arg!(--quiet <YES_NO> "Suppress output by default (y/n)")
+            .value_parser(clap::builder::BoolishValueParser::new()),
+        arg!(--"extract-title" <YES_NO> "Extract titles from line 1 content by default (y/n)")
+            .value_parser(clap::builder::BoolishValueParser::new()),
+        arg!(--"syndicate-to" <TARGETS> "Default syndication targets, as comma-separated names or UIDs")
+            .value_parser(value_parser!(String)),
+        arg!(--destination <DESTINATION> "Default destination, given by UID or name")
+            .value_parser(value_parser!(String)),
+        arg!(--"token-command" <COMMAND> "Read the token from the output of COMMAND instead of storing it")
+            .value_parser(value_parser!(String))
+            .conflicts_with_all(["token-env", "token-file"]),
+        arg!(--"token-env" <VARIABLE> "Read the token from the environment variable VARIABLE instead of storing it")
+            .value_parser(value_parser!(String))
+            .conflicts_with("token-file"),
+        arg!(--"token-file" <PATH> "Read the token from the file at PATH instead of storing it")
+            .value_parser(value_parser!(String)),
+        arg!(--default "Make the profile the default profile")
+            .action(clap::ArgAction::SetTrue),
+        arg!(--"non-interactive" "Do not prompt; keep the current settings of an existing profile")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+/// Creates arguments for the config server command.
+fn config_server_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(--json "Print the capabilities as JSON").action(clap::ArgAction::SetTrue),
+        arg!(--cached "Print the cached capabilities instead of querying the server")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
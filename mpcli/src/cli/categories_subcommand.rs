@@ -0,0 +1,66 @@
+//! Handle the 'categories' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::query_categories;
+
+use crate::cli::overrides_from_args;
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::profile::Profile;
+use crate::configuration::server_config_cache::ServerConfigCache;
+
+/// Handle the 'categories' subcommand.
+pub async fn handle_categories_subcommand(matches: &ArgMatches) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
+        eprintln!("Error loading configuration:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let search = matches.get_one::<String>("search").map(String::as_str);
+
+    match query_categories(&profile.service, search).await {
+        Ok(categories) => {
+            for category in categories {
+                println!("{}", category);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error querying categories:");
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Warn about categories that are not used on any existing post, as they are likely typos.
+/// Nothing is reported if the server does not support category queries.
+pub async fn warn_about_new_categories(categories: &[String], profile: &Profile) {
+    if categories.is_empty() {
+        return;
+    }
+
+    // Servers that do not list their queries may still support `q=category`, so only skip the
+    // check if the server lists its queries and `category` is not among them.
+    if let Ok(server_config) = ServerConfigCache::load_or_refresh(profile).await
+        && !server_config.queries.is_empty()
+        && !server_config.supports_query("category")
+    {
+        return;
+    }
+
+    let Ok(existing_categories) = query_categories(&profile.service, None).await else {
+        return;
+    };
+
+    for category in categories {
+        let is_known = existing_categories
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(category));
+        if !is_known {
+            eprintln!(
+                "Warning: category '{}' is not used on any existing post. Is it a typo?",
+                category
+            );
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Handle the 'config' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::ServerConfig;
+use similar::TextDiff;
+use std::{fs, io};
+
+use crate::cli::overrides_from_args;
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::config_error::ConfigError;
+use crate::configuration::config_key::ConfigKey;
+use crate::configuration::migration::CURRENT_CONFIG_VERSION;
+use crate::configuration::overrides::EffectiveProfile;
+use crate::configuration::server_config_cache::ServerConfigCache;
+
+/// Handle the 'config' subcommand.
+pub async fn handle_config_subcommand(matches: &ArgMatches) {
+    if let Some(show_matches) = matches.subcommand_matches("show") {
+        handle_show_subcommand(show_matches);
+    } else if matches.subcommand_matches("path").is_some() {
+        match AppConfig::get_config_file_path() {
+            Ok(config_path) => println!("{}", config_path),
+            Err(e) => exit_with_error(&e.into()),
+        }
+    } else if let Some(migrate_matches) = matches.subcommand_matches("migrate") {
+        if let Err(e) = migrate_config_file(migrate_matches.get_flag("dry-run")) {
+            exit_with_error(&e);
+        }
+    } else if let Some(get_matches) = matches.subcommand_matches("get") {
+        match get_setting(get_matches) {
+            Ok(Some(value)) => println!("{}", value),
+            // Like `git config`, report a setting that is not set with the exit status only.
+            Ok(None) => std::process::exit(1),
+            Err(e) => exit_with_error(&e),
+        }
+    } else if let Some(set_matches) = matches.subcommand_matches("set") {
+        let value = set_matches.get_one::<String>("value").unwrap();
+        if let Err(e) = change_setting(set_matches, |key, app_config, profile_name| {
+            key.set(app_config, profile_name, value)
+        }) {
+            exit_with_error(&e);
+        }
+    } else if let Some(unset_matches) = matches.subcommand_matches("unset") {
+        if let Err(e) = change_setting(unset_matches, |key, app_config, profile_name| {
+            key.unset(app_config, profile_name)
+        }) {
+            exit_with_error(&e);
+        }
+    } else if let Some(server_matches) = matches.subcommand_matches("server") {
+        handle_server_subcommand(server_matches).await;
+    }
+}
+
+/// Upgrade the configuration file to the current layout, printing the changes as a diff.
+fn migrate_config_file(dry_run: bool) -> Result<(), ConfigError> {
+    let config_path = AppConfig::get_config_file_path()?;
+    let config_content = fs::read_to_string(&config_path)?;
+    let app_config = AppConfig::parse(&config_content)?;
+
+    if app_config.loaded_version() == Some(CURRENT_CONFIG_VERSION) {
+        println!(
+            "The configuration file is already at version {}.",
+            CURRENT_CONFIG_VERSION
+        );
+        return Ok(());
+    }
+
+    let migrated_content = app_config.to_toml()?;
+    let diff = TextDiff::from_lines(&config_content, &migrated_content);
+    print!(
+        "{}",
+        diff.unified_diff()
+            .header(&config_path, &format!("{} (migrated)", config_path))
+    );
+
+    if dry_run {
+        println!("Dry run: the configuration file was not changed.");
+    } else {
+        app_config.save()?;
+    }
+    Ok(())
+}
+
+/// Get the value of the setting named by the 'key' argument in the selected profile.
+fn get_setting(matches: &ArgMatches) -> Result<Option<String>, ConfigError> {
+    let key: ConfigKey = matches.get_one::<String>("key").unwrap().parse()?;
+    let app_config = AppConfig::load()?;
+    let profile_name = selected_profile_name(matches, &app_config);
+    key.get(&app_config, &profile_name)
+}
+
+/// Change the setting named by the 'key' argument in the selected profile and save the
+/// configuration file.
+fn change_setting(
+    matches: &ArgMatches,
+    change: impl FnOnce(ConfigKey, &mut AppConfig, &str) -> Result<(), ConfigError>,
+) -> Result<(), ConfigError> {
+    let key: ConfigKey = matches.get_one::<String>("key").unwrap().parse()?;
+    let mut app_config = AppConfig::load_or_new()?;
+    let profile_name = selected_profile_name(matches, &app_config);
+    change(key, &mut app_config, &profile_name)?;
+    app_config.save()?;
+    Ok(())
+}
+
+/// Get the name of the profile given with `--profile`, or the default profile.
+fn selected_profile_name(matches: &ArgMatches, app_config: &AppConfig) -> String {
+    overrides_from_args(matches)
+        .profile_name()
+        .cloned()
+        .unwrap_or_else(|| app_config.default_profile.clone())
+}
+
+fn exit_with_error(e: &ConfigError) -> ! {
+    eprintln!("Error: {}", e);
+    std::process::exit(1);
+}
+
+/// Handle the 'config show' subcommand.
+fn handle_show_subcommand(matches: &ArgMatches) {
+    let output = if matches.get_flag("effective") {
+        AppConfig::load_effective_profile(&overrides_from_args(matches))
+            .map(|effective| effective_profile_as_string(&effective))
+    } else {
+        AppConfig::load().and_then(|app_config| redacted_config_as_string(&app_config))
+    };
+
+    match output {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("Error loading configuration:");
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Format the configuration file as TOML, with the tokens redacted.
+fn redacted_config_as_string(app_config: &AppConfig) -> io::Result<String> {
+    let mut redacted = AppConfig::new();
+    redacted.default_profile = app_config.default_profile.clone();
+    for profile in app_config.profiles.values() {
+        let mut profile = profile.clone();
+        profile.service.auth_token = redact(&profile.service.auth_token);
+        redacted.set_profile(profile);
+    }
+
+    let content = toml::to_string(&redacted).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to serialize config: {}", e),
+        )
+    })?;
+    Ok(format!(
+        "# {}\n\n{}",
+        AppConfig::get_config_file_path()?,
+        content.trim_end()
+    ))
+}
+
+/// Format the effective settings and their origins as a human-readable string.
+fn effective_profile_as_string(effective: &EffectiveProfile) -> String {
+    let config_path = AppConfig::get_config_file_path().unwrap_or_else(|_| "(unknown)".to_string());
+    let service = &effective.profile.service;
+
+    [
+        (
+            "Config file",
+            config_path,
+            AppConfig::config_file_path_origin(),
+        ),
+        (
+            "Profile",
+            effective.profile.name.clone(),
+            effective.profile_origin.clone(),
+        ),
+        (
+            "API URL",
+            service.api_url.clone(),
+            effective.api_url_origin.clone(),
+        ),
+        (
+            "Token",
+            redact(&service.auth_token),
+            effective.token_origin.clone(),
+        ),
+    ]
+    .iter()
+    .map(|(name, value, origin)| {
+        format!("{:<12} {} (from {})", format!("{}:", name), value, origin)
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Hide a token, keeping only its last four characters if it is long enough that they do not
+/// give much of it away.
+fn redact(token: &str) -> String {
+    let length = token.chars().count();
+    if length == 0 {
+        String::new()
+    } else if length < 16 {
+        "********".to_string()
+    } else {
+        let last_four: String = token.chars().skip(length - 4).collect();
+        format!("********{}", last_four)
+    }
+}
+
+/// Handle the 'config server' subcommand.
+async fn handle_server_subcommand(matches: &ArgMatches) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
+        eprintln!("Error loading configuration:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let server_config = if matches.get_flag("cached") {
+        match ServerConfigCache::load(&profile) {
+            Ok(Some(server_config)) => server_config,
+            Ok(None) => {
+                eprintln!("Error: no server configuration is cached.");
+                eprintln!("Run 'mp config server' to query the server.");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error loading server configuration cache:");
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        ServerConfigCache::refresh(&profile)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error querying server configuration:");
+                eprintln!("{}", e);
+                std::process::exit(1);
+            })
+    };
+
+    if matches.get_flag("json") {
+        let json = serde_json::to_string_pretty(&server_config)
+            .expect("server configuration is serializable");
+        println!("{}", json);
+    } else {
+        println!("{}", server_config_as_string(&server_config));
+    }
+}
+
+/// Format a ServerConfig as a human-readable string.
+fn server_config_as_string(server_config: &ServerConfig) -> String {
+    let none = || "(none)".to_string();
+
+    let media_endpoint = server_config.media_endpoint.clone().unwrap_or_else(none);
+    let queries = if server_config.queries.is_empty() {
+        none()
+    } else {
+        server_config.queries.join(", ")
+    };
+    let post_types = if server_config.post_types.is_empty() {
+        none()
+    } else {
+        server_config
+            .post_types
+            .iter()
+            .map(|t| t.post_type.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut output = format!(
+        "Media endpoint: {}\nQueries:        {}\nPost types:     {}",
+        media_endpoint, queries, post_types
+    );
+
+    output.push_str("\nSyndication targets:");
+    push_named_list(
+        &mut output,
+        server_config
+            .syndicate_to
+            .iter()
+            .map(|t| (t.name.as_str(), t.uid.as_str())),
+    );
+
+    output.push_str("\nDestinations:");
+    push_named_list(
+        &mut output,
+        server_config
+            .destinations
+            .iter()
+            .map(|d| (d.name.as_str(), d.uid.as_str())),
+    );
+
+    output
+}
+
+/// Append a list of (name, uid) pairs, one per line, to the output.
+fn push_named_list<'a>(output: &mut String, items: impl Iterator<Item = (&'a str, &'a str)>) {
+    let mut is_empty = true;
+    for (name, uid) in items {
+        is_empty = false;
+        output.push_str(&format!("\n  - {} ({})", name, uid));
+    }
+    if is_empty {
+        output.push_str(" (none)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(redact(""), "");
+        assert_eq!(redact("short"), "********");
+        assert_eq!(redact("0123456789abcdefWXYZ"), "********WXYZ");
+    }
+}
@@ -1,103 +1,413 @@
 //! Handle the 'configure' subcommand for the CLI application.
 
-use mplib::MicropubService;
-use rustyline::DefaultEditor;
-use std::io;
-
+use crate::cli::overrides_from_args;
+use crate::cli::user_input::{get_user_input, user_confirms};
+use crate::cli::whoami_subcommand::warn_about_missing_scopes;
 use crate::configuration::app_config::AppConfig;
+use crate::configuration::config_error::ConfigError;
 use crate::configuration::default_behavior::DefaultBehavior;
+use crate::configuration::profile::Profile;
+use crate::configuration::server_config_cache::ServerConfigCache;
+use crate::configuration::token_source::TokenSource;
+use clap::ArgMatches;
+use mplib::{Destination, MicropubService, RequestFormat, discover_endpoints, verify_token};
 
 /// Handle the 'configure' subcommand.
-pub fn handle_configure_subcommand() {
-    if let Err(e) = configure_app() {
+pub async fn handle_configure_subcommand(matches: &ArgMatches) {
+    if let Err(e) = configure_app(&ConfigureArgs::from_matches(matches)).await {
         eprintln!("Error during configuration:");
         eprintln!("{}", e);
         std::process::exit(1);
     }
 }
 
-fn configure_app() -> io::Result<()> {
-    if AppConfig::config_file_exists() && !user_opts_to_update_existing_config() {
+/// The settings given as flags to the 'configure' subcommand. Settings that are not given are
+/// prompted for, unless running non-interactively, in which case an existing profile keeps its
+/// current values.
+struct ConfigureArgs {
+    profile_name: Option<String>,
+    api_url: Option<String>,
+    token: Option<String>,
+    token_source: Option<TokenSource>,
+    request_format: Option<RequestFormat>,
+    quiet: Option<bool>,
+    extract_title: Option<bool>,
+    syndicate_to: Option<String>,
+    destination: Option<String>,
+    make_default: bool,
+    interactive: bool,
+}
+
+impl ConfigureArgs {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let overrides = overrides_from_args(matches);
+        ConfigureArgs {
+            profile_name: overrides.profile.map(|setting| setting.value),
+            api_url: overrides.api_url.map(|setting| setting.value),
+            token: overrides.token.map(|setting| setting.value),
+            token_source: token_source_from_matches(matches),
+            request_format: matches
+                .get_one::<String>("request-format")
+                .map(|format| match format.as_str() {
+                    "json" => RequestFormat::Json,
+                    _ => RequestFormat::Form,
+                }),
+            quiet: matches.get_one::<bool>("quiet").copied(),
+            extract_title: matches.get_one::<bool>("extract-title").copied(),
+            syndicate_to: matches.get_one::<String>("syndicate-to").cloned(),
+            destination: matches.get_one::<String>("destination").cloned(),
+            make_default: matches.get_flag("default"),
+            interactive: !matches.get_flag("non-interactive"),
+        }
+    }
+
+    /// Use the value given as a flag, or prompt for it when running interactively. The current
+    /// value is kept if the user enters nothing, or if running non-interactively.
+    fn value_or_prompt(&self, value: &Option<String>, prompt: &str, current: &str) -> String {
+        if let Some(value) = value {
+            return value.clone();
+        }
+        if !self.interactive {
+            return current.to_string();
+        }
+
+        let input = if current.is_empty() {
+            get_user_input(prompt)
+        } else {
+            get_user_input(&format!("{} (leave blank to keep '{}')", prompt, current))
+        };
+        if input.is_empty() {
+            current.to_string()
+        } else {
+            input
+        }
+    }
+
+    /// Like `value_or_prompt`, for a yes-or-no question.
+    fn bool_or_prompt(&self, value: Option<bool>, prompt: &str, current: bool) -> bool {
+        if let Some(value) = value {
+            return value;
+        }
+        if !self.interactive {
+            return current;
+        }
+
+        let choices = if current { "Y/n" } else { "y/N" };
+        let input = get_user_input(&format!("{} ({})", prompt, choices));
+        if input.eq_ignore_ascii_case("y") {
+            true
+        } else if input.eq_ignore_ascii_case("n") {
+            false
+        } else {
+            current
+        }
+    }
+}
+
+/// Get the token source given by the `--token-command`, `--token-env`, or `--token-file` flag.
+fn token_source_from_matches(matches: &ArgMatches) -> Option<TokenSource> {
+    let token_source = TokenSource {
+        token_command: matches.get_one::<String>("token-command").cloned(),
+        token_env: matches.get_one::<String>("token-env").cloned(),
+        token_file: matches.get_one::<String>("token-file").cloned(),
+    };
+    token_source.is_configured().then_some(token_source)
+}
+
+async fn configure_app(args: &ConfigureArgs) -> Result<(), ConfigError> {
+    let mut app_config = AppConfig::load_or_new()?;
+    let profile_name = args
+        .profile_name
+        .clone()
+        .unwrap_or_else(|| app_config.default_profile.clone());
+
+    let existing = app_config.profiles.get(&profile_name).cloned();
+
+    if existing.is_some()
+        && args.interactive
+        && !user_opts_to_update_existing_profile(&profile_name)
+    {
         println!("Configuration not updated. Exiting.");
         return Ok(());
     }
 
-    let app_config = AppConfig::from_user_input()?;
+    // An existing profile keeps the settings that are not given.
+    let mut profile = existing.unwrap_or_else(|| {
+        Profile::new(
+            profile_name.clone(),
+            MicropubService::new(String::new(), String::new()),
+            DefaultBehavior::new(false, false, Vec::new()),
+        )
+    });
+    let previous_api_url = profile.service.api_url.clone();
+    configure_service(&mut profile, args)?;
+    if profile.service.api_url != previous_api_url {
+        profile.service.token_endpoint = None;
+        discover_service_endpoints(&mut profile.service).await;
+    }
+
+    // Verify the token the profile will use, which may be read from its token source.
+    let mut effective_profile = profile.clone();
+    let verification = match effective_profile.resolve_token() {
+        Ok(_) => verify_token(&effective_profile.service).await,
+        Err(e) => Err(e.into()),
+    };
+    match verification {
+        Ok(token_info) => {
+            if let Some(me) = &token_info.me {
+                println!("The token is valid for {}.", me);
+            }
+            warn_about_missing_scopes(&token_info);
+        }
+        Err(e) => {
+            eprintln!("Warning: could not verify the authentication token:");
+            eprintln!("{}", e);
+            if args.interactive && !user_confirms("Do you want to save the profile anyway") {
+                println!("Configuration not updated. Exiting.");
+                return Ok(());
+            }
+        }
+    }
+
+    configure_default_behavior(&mut profile.default_behavior, args);
+
+    // Cache the server's capabilities so later commands can check options against them.
+    match ServerConfigCache::refresh(&effective_profile).await {
+        Ok(server_config) => {
+            let destination = &mut profile.default_behavior.destination;
+            match &args.destination {
+                Some(given) => *destination = Some(given.clone()),
+                None if args.interactive => {
+                    *destination =
+                        choose_default_destination(&server_config.destinations, destination.take())
+                }
+                None => {}
+            }
+        }
+        Err(e) => {
+            if let Some(destination) = &args.destination {
+                profile.default_behavior.destination = Some(destination.clone());
+            }
+            eprintln!("Warning: could not query the server configuration:");
+            eprintln!("{}", e);
+        }
+    }
+
+    // The first profile becomes the default; later ones only if the user wants them to.
+    let make_default = app_config.profiles.is_empty()
+        || args.make_default
+        || (args.interactive
+            && app_config.default_profile != profile_name
+            && user_confirms(&format!(
+                "Do you want to make '{}' the default profile",
+                profile_name
+            )));
+    if make_default {
+        app_config.default_profile = profile_name.clone();
+    }
+
+    app_config.set_profile(profile.clone());
     let app_config_path = app_config.save()?;
 
     println!(
-        "Configuration saved successfully in file '{}'.",
-        app_config_path
+        "Profile '{}' saved successfully in file '{}'.",
+        profile_name, app_config_path
     );
 
     Ok(())
 }
 
-fn user_opts_to_update_existing_config() -> bool {
+/// If the API URL entered is a site advertising a Micropub endpoint, use that endpoint and the
+/// site's token endpoint. Otherwise, the API URL is assumed to be the endpoint itself: endpoints
+/// commonly answer a plain GET request with an error.
+async fn discover_service_endpoints(service: &mut MicropubService) {
+    let endpoints = match discover_endpoints(&service.api_url).await {
+        Ok(endpoints) => endpoints,
+        Err(e) if e.is_transport_failure() => {
+            eprintln!(
+                "Warning: could not reach '{}' to discover its endpoints:",
+                service.api_url
+            );
+            eprintln!("{}", e);
+            eprintln!("Assuming it is the Micropub endpoint itself.");
+            return;
+        }
+        Err(_) => return,
+    };
+    match endpoints.micropub {
+        Some(micropub) => {
+            println!("Found Micropub endpoint '{}'.", micropub);
+            service.api_url = micropub;
+            service.token_endpoint = endpoints.token_endpoint;
+        }
+        None if endpoints.is_html => {
+            eprintln!(
+                "Warning: '{}' looks like a homepage, but it does not advertise a Micropub endpoint.",
+                service.api_url
+            );
+            eprintln!("Check the URL if it is not the Micropub endpoint itself.");
+        }
+        None => {}
+    }
+}
+
+/// Let the user choose the default destination among those the server advertises, keeping the
+/// current one if they enter nothing. Returns None if the server advertises at most one
+/// destination or the user chooses the server's default.
+fn choose_default_destination(
+    destinations: &[Destination],
+    current: Option<String>,
+) -> Option<String> {
+    if destinations.len() < 2 {
+        return None;
+    }
+
+    println!();
+    println!("===================================================");
+    println!("Choose the blog to publish to by default.");
+    println!("This can be overridden via the --destination flag.");
+    println!("===================================================");
+
+    for (index, destination) in destinations.iter().enumerate() {
+        println!(
+            "  {}. {} ({})",
+            index + 1,
+            destination.name,
+            destination.uid
+        );
+    }
+
+    loop {
+        let choice = match &current {
+            Some(current) => get_user_input(&format!(
+                "Enter a number from 1 to {}, or 0 for the server's default (leave blank to keep '{}')",
+                destinations.len(),
+                current
+            )),
+            None => get_user_input(&format!(
+                "Enter a number from 1 to {} (leave blank for the server's default)",
+                destinations.len()
+            )),
+        };
+        if choice.is_empty() {
+            return current;
+        }
+        if choice == "0" {
+            return None;
+        }
+        match choice.parse::<usize>() {
+            Ok(number) if (1..=destinations.len()).contains(&number) => {
+                return Some(destinations[number - 1].uid.clone());
+            }
+            _ => println!("'{}' is not a valid choice.", choice),
+        }
+    }
+}
+
+fn user_opts_to_update_existing_profile(profile_name: &str) -> bool {
     let config_file_path =
         AppConfig::get_config_file_path().unwrap_or_else(|_| String::from("unknown path"));
 
     println!(
-        "Configuration file already exists in file '{}'.",
-        config_file_path
+        "Profile '{}' already exists in file '{}'.",
+        profile_name, config_file_path
     );
     let response = get_user_input("Do you want to update it (y/N)? ");
 
     response.trim().eq_ignore_ascii_case("y")
 }
 
-trait UserInputConfigurable: Sized {
-    fn from_user_input() -> io::Result<Self>;
-}
-
-impl UserInputConfigurable for AppConfig {
-    fn from_user_input() -> io::Result<Self> {
-        let microblog_service = MicropubService::from_user_input()?;
-        let default_behavior = DefaultBehavior::from_user_input()?;
-
-        let app_config = AppConfig::new(microblog_service, default_behavior);
-        Ok(app_config)
-    }
-}
-
-impl UserInputConfigurable for MicropubService {
-    fn from_user_input() -> io::Result<Self> {
+/// Update the service settings of a profile from the flags, or by prompting for them.
+fn configure_service(profile: &mut Profile, args: &ConfigureArgs) -> Result<(), ConfigError> {
+    if args.interactive {
         println!();
         println!("==============================================");
         println!("Define the micopub API endpoint to publish to.");
         println!("==============================================");
+    }
+
+    // A token source given as a flag replaces the profile's; otherwise the profile keeps its own.
+    if let Some(token_source) = &args.token_source {
+        profile.token_source = token_source.clone();
+    }
+    let service = &mut profile.service;
 
-        let api_url = get_user_input("[Step 1 of 2] Enter API URL");
-        let auth_token = get_user_input("[Step 2 of 2] Enter Authentication Token");
+    service.api_url = args.value_or_prompt(
+        &args.api_url,
+        "[Step 1 of 3] Enter API URL, or the URL of your site to discover it",
+        &service.api_url,
+    );
+    if service.api_url.is_empty() {
+        return Err(ConfigError::MissingValue("API URL".to_string()));
+    }
 
-        Self::from_args(api_url, auth_token)
+    // The current token is not shown, and is kept if the user enters nothing.
+    let prompt = if profile.token_source.is_configured() {
+        format!(
+            "[Step 2 of 3] Enter Authentication Token (leave blank to read it from {})",
+            profile.token_source.key()
+        )
+    } else if service.auth_token.is_empty() {
+        "[Step 2 of 3] Enter Authentication Token".to_string()
+    } else {
+        "[Step 2 of 3] Enter Authentication Token (leave blank to keep the current token)"
+            .to_string()
+    };
+    if let Some(token) = args
+        .token
+        .clone()
+        .or_else(|| args.interactive.then(|| get_user_input(&prompt)))
+        .filter(|token| !token.is_empty())
+    {
+        service.auth_token = token;
     }
+    // With a token source, a token stored in the configuration file is only a fallback.
+    if service.auth_token.is_empty() && !profile.token_source.is_configured() {
+        return Err(ConfigError::MissingValue(
+            "authentication token".to_string(),
+        ));
+    }
+
+    let send_json = args.bool_or_prompt(
+        args.request_format
+            .map(|format| format == RequestFormat::Json),
+        "[Step 3 of 3] Send requests as JSON instead of form-encoded",
+        service.request_format == RequestFormat::Json,
+    );
+    service.request_format = if send_json {
+        RequestFormat::Json
+    } else {
+        RequestFormat::Form
+    };
+
+    Ok(())
 }
 
-impl UserInputConfigurable for DefaultBehavior {
-    fn from_user_input() -> io::Result<Self> {
+/// Update the default behavior of a profile from the flags, or by prompting for it. Settings
+/// `configure` does not ask about, such as the timeout, are kept.
+fn configure_default_behavior(default_behavior: &mut DefaultBehavior, args: &ConfigureArgs) {
+    if args.interactive {
         println!();
         println!("=======================================================");
         println!("Define default behavior for the application.");
         println!("These options can be overridden via command-line flags.");
         println!("=======================================================");
-
-        let quiet =
-            get_user_input("[Step 1 of 2] Enable quiet mode, which suppresses output (y/N)");
-        let extract_title =
-            get_user_input("[Step 2 of 2] Extract titles from line 1 content (Y/n)");
-
-        Self::from_args(quiet, extract_title)
     }
-}
 
-fn get_user_input(prompt: &str) -> String {
-    let prompt = format!("{}: ", prompt);
-    let mut rl = DefaultEditor::new().expect("Failed to create readline editor");
-    let readline = rl.readline(&prompt);
-
-    match readline {
-        Ok(line) => line.trim().to_string(),
-        Err(_) => String::new(),
-    }
+    default_behavior.quiet = args.bool_or_prompt(
+        args.quiet,
+        "[Step 1 of 3] Enable quiet mode, which suppresses output",
+        default_behavior.quiet,
+    );
+    default_behavior.extract_title = args.bool_or_prompt(
+        args.extract_title,
+        "[Step 2 of 3] Extract titles from line 1 content",
+        default_behavior.extract_title,
+    );
+    let syndicate_to = args.value_or_prompt(
+        &args.syndicate_to,
+        "[Step 3 of 3] Default syndication targets, as comma-separated names or UIDs (optional)",
+        &default_behavior.syndicate_to.join(", "),
+    );
+    default_behavior.syndicate_to = DefaultBehavior::parse_syndication_targets(&syndicate_to);
 }
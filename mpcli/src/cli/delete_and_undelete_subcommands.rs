@@ -0,0 +1,91 @@
+//! Handle the 'delete' and 'undelete' subcommands for the CLI application.
+
+use clap::ArgMatches;
+use mplib::{PostError, delete_post, undelete_post};
+
+use crate::cli::overrides_from_args;
+use crate::cli::user_input::user_confirms;
+use crate::configuration::app_config::AppConfig;
+
+/// The action performed on a post by the 'delete' and 'undelete' subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteAction {
+    Delete,
+    Undelete,
+}
+
+impl DeleteAction {
+    /// Get the name of the Micropub action.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeleteAction::Delete => "delete",
+            DeleteAction::Undelete => "undelete",
+        }
+    }
+}
+
+/// Handle the 'delete' or 'undelete' subcommand.
+pub async fn handle_delete_or_undelete_subcommand(action: DeleteAction, matches: &ArgMatches) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
+        eprintln!("Error loading configuration:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let url = matches
+        .get_one::<String>("url")
+        .expect("url is a required argument");
+
+    if !matches.get_flag("yes")
+        && !user_confirms(&format!(
+            "Do you want to {} the post at '{}'",
+            action.as_str(),
+            url
+        ))
+    {
+        println!("Post not {}d. Exiting.", action.as_str());
+        return;
+    }
+
+    let result = match action {
+        DeleteAction::Delete => delete_post(url, &profile.service).await,
+        DeleteAction::Undelete => undelete_post(url, &profile.service).await,
+    };
+
+    let quiet = profile.default_behavior.quiet || matches.get_flag("quiet");
+
+    match result {
+        Ok(()) => {
+            if quiet {
+                return;
+            }
+            println!(
+                "Post {}d successfully.\n\nURL:     {}",
+                action.as_str(),
+                url
+            );
+        }
+        Err(PostError::InsufficientScope(description)) => {
+            eprintln!(
+                "Error: your authentication token is not allowed to {} posts.",
+                action.as_str()
+            );
+            eprintln!("{}", description);
+            std::process::exit(1);
+        }
+        Err(PostError::InvalidRequest(description)) => {
+            eprintln!(
+                "Error: the server rejected the {} request.",
+                action.as_str()
+            );
+            eprintln!("It may not support the '{}' action.", action.as_str());
+            eprintln!("{}", description);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error performing {}:", action.as_str());
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Handle the 'edit' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::{PostUpdate, Properties, update_post};
+use std::{fs, io, io::Read};
+
+use crate::cli::overrides_from_args;
+use crate::configuration::app_config::AppConfig;
+
+/// Handle the 'edit' subcommand.
+pub async fn handle_edit_subcommand(matches: &ArgMatches) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
+        eprintln!("Error loading configuration:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let update = post_update_from_args(matches).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
+    if update.is_empty() {
+        eprintln!("Error: no changes were specified");
+        eprintln!("For more information, try 'mp edit --help'.");
+        std::process::exit(1);
+    }
+
+    let quiet = profile.default_behavior.quiet || matches.get_flag("quiet");
+
+    match update_post(update, &profile.service).await {
+        Ok(url) => {
+            if quiet {
+                return;
+            }
+            println!("Post updated successfully.\n\nURL:     {}", url);
+        }
+        Err(e) => {
+            eprintln!("Error updating post:");
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Construct a PostUpdate from CLI arguments.
+fn post_update_from_args(matches: &ArgMatches) -> Result<PostUpdate, String> {
+    let url = matches
+        .get_one::<String>("url")
+        .cloned()
+        .expect("url is a required argument");
+    let mut update = PostUpdate::new(url);
+
+    if let Some(content) =
+        get_content_from_cli_args(matches).map_err(|e| format!("Error reading content: {}", e))?
+    {
+        update.replace.push_text("content", &content);
+    }
+    if let Some(title) = matches.get_one::<String>("title") {
+        update.replace.push_text("name", title);
+    }
+    for category in values_of(matches, "category") {
+        update.replace.push_text("category", category);
+    }
+    for category in values_of(matches, "add-category") {
+        update.add.push_text("category", category);
+    }
+    for category in values_of(matches, "remove-category") {
+        update.delete_values.push_text("category", category);
+    }
+
+    push_property_assignments(&mut update.replace, matches, "replace")?;
+    push_property_assignments(&mut update.add, matches, "add")?;
+    push_property_assignments(&mut update.delete_values, matches, "remove")?;
+
+    update.delete_properties = values_of(matches, "delete").cloned().collect();
+
+    Ok(update)
+}
+
+/// Retrieve the replacement content from CLI arguments, if any was given.
+fn get_content_from_cli_args(matches: &ArgMatches) -> Result<Option<String>, io::Error> {
+    if matches.get_flag("stdin") {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        Ok(Some(buffer))
+    } else if let Some(file_path) = matches.get_one::<String>("file") {
+        fs::read_to_string(file_path).map(Some)
+    } else {
+        Ok(matches.get_one::<String>("content").cloned())
+    }
+}
+
+/// Add each `PROPERTY=VALUE` argument of the given id to the properties.
+fn push_property_assignments(
+    properties: &mut Properties,
+    matches: &ArgMatches,
+    id: &str,
+) -> Result<(), String> {
+    for assignment in values_of(matches, id) {
+        let (name, value) = parse_property_assignment(assignment)?;
+        properties.push_text(name, value);
+    }
+    Ok(())
+}
+
+/// Split a `PROPERTY=VALUE` argument into its property name and value.
+fn parse_property_assignment(assignment: &str) -> Result<(&str, &str), String> {
+    match assignment.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim(), value)),
+        _ => Err(format!(
+            "'{}' is not a valid property assignment; expected PROPERTY=VALUE",
+            assignment
+        )),
+    }
+}
+
+/// Get all values of a repeatable argument.
+fn values_of<'a>(matches: &'a ArgMatches, id: &str) -> impl Iterator<Item = &'a String> {
+    matches.get_many::<String>(id).into_iter().flatten()
+}
@@ -0,0 +1,101 @@
+//! Compose posts in the user's text editor.
+
+use directories::ProjectDirs;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Open the user's editor (`$VISUAL`, then `$EDITOR`) on a temporary file pre-filled with
+/// `template`, and return the saved contents.
+///
+/// If the editor fails, the temporary file is kept and its path is reported in the error, so
+/// nothing typed is lost.
+pub fn compose_in_editor(template: &str) -> io::Result<String> {
+    let path = env::temp_dir().join(format!("mp-post-{}.md", unique_suffix()));
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| io::Write::write_all(&mut file, template.as_bytes()))?;
+
+    let editor = editor_command();
+    let status = shell_command(&format!("{} \"{}\"", editor, path.display())).status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            return Err(io::Error::other(format!(
+                "Editor '{}' exited with {}. The text was kept in '{}'.",
+                editor,
+                status,
+                path.display()
+            )));
+        }
+        Err(e) => {
+            return Err(io::Error::new(
+                e.kind(),
+                format!("Could not run editor '{}': {}", editor, e),
+            ));
+        }
+    }
+
+    let content = fs::read_to_string(&path)?;
+    fs::remove_file(&path)?;
+    Ok(content)
+}
+
+/// Save text that could not be published in the recovery directory, and return its path.
+pub fn save_for_recovery(content: &str) -> io::Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("", "", "mp").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not determine project directories",
+        )
+    })?;
+
+    let recovery_dir = proj_dirs.data_dir().join("recovery");
+    fs::create_dir_all(&recovery_dir)?;
+
+    let path = recovery_dir.join(format!("post-{}.md", unique_suffix()));
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// Get the editor to run, as a shell command.
+fn editor_command() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "windows") {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        })
+}
+
+/// Build a command that runs `command_line` through the shell, so that editors configured with
+/// arguments, such as `code --wait`, work.
+fn shell_command(command_line: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", command_line]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", command_line]);
+        command
+    }
+}
+
+/// A suffix that keeps file names from different runs apart.
+fn unique_suffix() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    format!("{}-{}", seconds, std::process::id())
+}
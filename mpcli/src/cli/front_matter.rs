@@ -0,0 +1,352 @@
+//! Parse the front matter at the start of post content, in the TOML (`+++`) and YAML (`---`)
+//! formats used by static site generators such as Hugo and Jekyll.
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// The TOML front matter delimiter.
+const TOML_DELIMITER: &str = "+++";
+
+/// The YAML front matter delimiter.
+const YAML_DELIMITER: &str = "---";
+
+/// Represents the post metadata given in front matter. Keys without a Micropub equivalent,
+/// such as the layout of a page, are ignored.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    /// Categories and tags, which Micropub does not distinguish.
+    pub categories: Vec<String>,
+    /// Publication date, from `published`, `publishDate`, or `date`.
+    pub published: Option<String>,
+    pub slug: Option<String>,
+    /// Whether the post is a draft, from `draft`, `status`, or Jekyll's `published: false`.
+    pub draft: Option<bool>,
+    pub syndicate_to: Vec<String>,
+    pub photos: Vec<FrontMatterPhoto>,
+    pub in_reply_to: Option<String>,
+}
+
+/// Represents a photo given in front matter, by path or URL, with optional alt text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontMatterPhoto {
+    pub source: String,
+    pub alt: Option<String>,
+}
+
+impl FrontMatter {
+    /// Read the front matter fields from a table of keys and values. Keys are matched
+    /// regardless of case, and `_` and `-` are interchangeable in them.
+    fn from_table(table: Map<String, Value>) -> Result<Self, String> {
+        let fields: HashMap<String, Value> = table
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key.to_lowercase().replace('_', "-"), value))
+            .collect();
+        let field = |keys: &[&'static str]| {
+            keys.iter()
+                .find_map(|key| fields.get(*key).map(|value| (*key, value)))
+        };
+        let string = |keys: &[&'static str]| field(keys).map(as_string).transpose();
+
+        let mut front_matter = FrontMatter {
+            title: string(&["title"])?,
+            slug: string(&["slug", "mp-slug"])?,
+            in_reply_to: string(&["in-reply-to"])?,
+            ..FrontMatter::default()
+        };
+
+        for key in ["categories", "category", "tags"] {
+            if let Some(value) = fields.get(key) {
+                for category in as_strings((key, value))? {
+                    if !front_matter.categories.contains(&category) {
+                        front_matter.categories.push(category);
+                    }
+                }
+            }
+        }
+        if let Some(value) = field(&["syndicate-to", "mp-syndicate-to"]) {
+            front_matter.syndicate_to = as_strings(value)?;
+        }
+
+        // Jekyll uses `published: false` to keep a post unpublished.
+        match fields.get("published") {
+            Some(Value::Bool(published)) => front_matter.draft = Some(!published),
+            Some(value) => front_matter.published = Some(as_string(("published", value))?),
+            None => {}
+        }
+        if front_matter.published.is_none() {
+            front_matter.published = string(&["publishdate", "date"])?;
+        }
+        if let Some(value) = field(&["draft"]) {
+            match value.1 {
+                Value::Bool(draft) => front_matter.draft = Some(*draft),
+                _ => return Err("'draft' must be true or false".to_string()),
+            }
+        }
+        if let Some(value) = field(&["status", "post-status"]) {
+            front_matter.draft = match as_string(value)?.to_lowercase().as_str() {
+                "draft" => Some(true),
+                "published" | "publish" => Some(false),
+                other => {
+                    return Err(format!(
+                        "'{}' is not a valid status; expected 'draft' or 'published'",
+                        other
+                    ));
+                }
+            };
+        }
+
+        if let Some((key, value)) = field(&["photos", "photo"]) {
+            let photos = match value {
+                Value::Array(photos) => photos.iter().collect(),
+                photo => vec![photo],
+            };
+            for photo in photos {
+                front_matter.photos.push(as_photo(key, photo)?);
+            }
+        }
+
+        Ok(front_matter)
+    }
+}
+
+/// Read a front matter value that should be a single string. Numbers are accepted as well.
+fn as_string((key, value): (&str, &Value)) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(format!("'{}' must be a string", key)),
+    }
+}
+
+/// Read a front matter value that should be a list of strings, or a single string.
+fn as_strings((key, value): (&str, &Value)) -> Result<Vec<String>, String> {
+    match value {
+        Value::Array(values) => values.iter().map(|value| as_string((key, value))).collect(),
+        value => Ok(vec![as_string((key, value))?]),
+    }
+}
+
+/// Read a photo given as a path or URL, or as a table with a `url` (or `src`) and an `alt` key.
+fn as_photo(key: &str, value: &Value) -> Result<FrontMatterPhoto, String> {
+    if let Value::Object(photo) = value {
+        let source = ["url", "src"]
+            .iter()
+            .find_map(|key| photo.get(*key))
+            .ok_or_else(|| format!("'{}' entries must have a 'url'", key))?;
+        let alt = photo
+            .get("alt")
+            .map(|alt| as_string(("alt", alt)))
+            .transpose()?;
+        Ok(FrontMatterPhoto {
+            source: as_string((key, source))?,
+            alt,
+        })
+    } else {
+        Ok(FrontMatterPhoto {
+            source: as_string((key, value))?,
+            alt: None,
+        })
+    }
+}
+
+/// Split content into its front matter, delimited by `+++` lines for TOML or `---` lines for
+/// YAML, and the remaining body. Content without front matter is returned unchanged with empty
+/// front matter.
+pub fn split_front_matter(content: &str) -> io::Result<(FrontMatter, String)> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid front matter: {}", message),
+        )
+    };
+
+    let (table, body) = if let Some((block, body)) = front_matter_block(content, TOML_DELIMITER) {
+        (parse_toml(block).map_err(invalid)?, body)
+    } else if let Some((block, body)) = front_matter_block(content, YAML_DELIMITER) {
+        (parse_yaml(block).map_err(invalid)?, body)
+    } else {
+        return Ok((FrontMatter::default(), content.to_string()));
+    };
+
+    let front_matter = FrontMatter::from_table(table).map_err(invalid)?;
+    Ok((
+        front_matter,
+        body.trim_start_matches(['\r', '\n']).to_string(),
+    ))
+}
+
+/// Parse a TOML front matter block into a table. Dates and times become strings.
+fn parse_toml(block: &str) -> Result<Map<String, Value>, String> {
+    fn to_json(value: toml::Value) -> Value {
+        match value {
+            toml::Value::String(s) => Value::String(s),
+            toml::Value::Integer(i) => Value::from(i),
+            toml::Value::Float(f) => Value::from(f),
+            toml::Value::Boolean(b) => Value::Bool(b),
+            toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+            toml::Value::Array(values) => Value::Array(values.into_iter().map(to_json).collect()),
+            toml::Value::Table(table) => Value::Object(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, to_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    let table: toml::Table = toml::from_str(block).map_err(|e| e.to_string())?;
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| (key, to_json(value)))
+        .collect())
+}
+
+/// Parse a YAML front matter block into a table. Floating-point numbers become strings.
+fn parse_yaml(block: &str) -> Result<Map<String, Value>, String> {
+    fn to_json(value: Yaml) -> Value {
+        match value {
+            Yaml::String(s) | Yaml::Real(s) => Value::String(s),
+            Yaml::Integer(i) => Value::from(i),
+            Yaml::Boolean(b) => Value::Bool(b),
+            Yaml::Array(values) => Value::Array(values.into_iter().map(to_json).collect()),
+            Yaml::Hash(hash) => Value::Object(
+                hash.into_iter()
+                    .filter_map(|(key, value)| Some((yaml_key(key)?, to_json(value))))
+                    .collect(),
+            ),
+            Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null,
+        }
+    }
+    fn yaml_key(key: Yaml) -> Option<String> {
+        match key {
+            Yaml::String(s) | Yaml::Real(s) => Some(s),
+            Yaml::Integer(i) => Some(i.to_string()),
+            Yaml::Boolean(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    let documents = YamlLoader::load_from_str(block).map_err(|e| e.to_string())?;
+    match documents.into_iter().next().map(to_json) {
+        Some(Value::Object(table)) => Ok(table),
+        None | Some(Value::Null) => Ok(Map::new()),
+        Some(_) => Err("expected a mapping of keys to values".to_string()),
+    }
+}
+
+/// Find a block delimited by lines consisting of `delimiter` at the very start of the content,
+/// and return it together with the content after the closing delimiter.
+fn front_matter_block<'a>(content: &'a str, delimiter: &str) -> Option<(&'a str, &'a str)> {
+    let rest = content.strip_prefix(delimiter)?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_toml_front_matter() {
+        let content =
+            "+++\ntitle = \"Hello\"\ncategories = [\"rust\", \"cli\"]\n+++\n\nThe body.\n";
+        let (front_matter, body) = split_front_matter(content).unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(front_matter.categories, vec!["rust", "cli"]);
+        assert_eq!(body, "The body.\n");
+    }
+
+    #[test]
+    fn test_split_hugo_toml_front_matter() {
+        let content = "+++\ntitle = \"Hello\"\ndate = 2024-05-01T09:30:00+02:00\ndraft = true\n\
+            tags = [\"rust\"]\ncategories = [\"code\", \"rust\"]\nslug = \"hello\"\n\
+            weight = 10\n+++\nThe body.\n";
+        let (front_matter, body) = split_front_matter(content).unwrap();
+        assert_eq!(
+            front_matter,
+            FrontMatter {
+                title: Some("Hello".to_string()),
+                categories: vec!["code".to_string(), "rust".to_string()],
+                published: Some("2024-05-01T09:30:00+02:00".to_string()),
+                slug: Some("hello".to_string()),
+                draft: Some(true),
+                ..FrontMatter::default()
+            }
+        );
+        assert_eq!(body, "The body.\n");
+    }
+
+    #[test]
+    fn test_split_jekyll_yaml_front_matter() {
+        let content = "---\nlayout: post\ntitle: \"Hello: world\"\ndate: 2024-05-01 09:30:00\n\
+            categories: notes\nin_reply_to: https://example.com/post\npublished: false\n\
+            syndicate-to: [https://mastodon.example/@me]\nphotos:\n  - cat.jpg\n\
+            \x20 - url: https://example.com/dog.jpg\n    alt: A dog\n---\n\nThe body.\n";
+        let (front_matter, body) = split_front_matter(content).unwrap();
+        assert_eq!(
+            front_matter,
+            FrontMatter {
+                title: Some("Hello: world".to_string()),
+                categories: vec!["notes".to_string()],
+                published: Some("2024-05-01 09:30:00".to_string()),
+                draft: Some(true),
+                syndicate_to: vec!["https://mastodon.example/@me".to_string()],
+                photos: vec![
+                    FrontMatterPhoto {
+                        source: "cat.jpg".to_string(),
+                        alt: None
+                    },
+                    FrontMatterPhoto {
+                        source: "https://example.com/dog.jpg".to_string(),
+                        alt: Some("A dog".to_string())
+                    },
+                ],
+                in_reply_to: Some("https://example.com/post".to_string()),
+                ..FrontMatter::default()
+            }
+        );
+        assert_eq!(body, "The body.\n");
+    }
+
+    #[test]
+    fn test_status_and_invalid_values() {
+        let (front_matter, _) = split_front_matter("---\nstatus: published\n---\n").unwrap();
+        assert_eq!(front_matter.draft, Some(false));
+
+        let error = split_front_matter("---\nstatus: hidden\n---\n").unwrap_err();
+        assert!(error.to_string().contains("'hidden' is not a valid status"));
+        let error = split_front_matter("---\ntitle: [a, b]\n---\n").unwrap_err();
+        assert!(error.to_string().contains("'title' must be a string"));
+        assert!(split_front_matter("---\njust text\n---\n").is_err());
+    }
+
+    #[test]
+    fn test_content_without_front_matter_is_unchanged() {
+        let content = "Just a body.\n+++\n";
+        let (front_matter, body) = split_front_matter(content).unwrap();
+        assert_eq!(front_matter, FrontMatter::default());
+        assert_eq!(body, content);
+    }
+
+    #[test]
+    fn test_unterminated_front_matter_is_treated_as_body() {
+        let content = "+++\ntitle = \"Hello\"\n";
+        let (front_matter, body) = split_front_matter(content).unwrap();
+        assert_eq!(front_matter, FrontMatter::default());
+        assert_eq!(body, content);
+    }
+}
@@ -0,0 +1,145 @@
+//! Handle the 'login' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::{DEFAULT_SCOPE, LoginSession, MicropubService};
+use std::io;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::cli::overrides_from_args;
+use crate::cli::user_input::user_confirms;
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::default_behavior::DefaultBehavior;
+use crate::configuration::profile::Profile;
+use crate::configuration::server_config_cache::ServerConfigCache;
+
+/// How long to wait for the user to approve the login in the browser.
+const LOGIN_TIMEOUT_SECS: u64 = 300;
+
+/// Handle the 'login' subcommand.
+pub async fn handle_login_subcommand(matches: &ArgMatches) {
+    let homepage = matches.get_one::<String>("homepage").unwrap();
+    let show_token = matches.get_flag("show-token");
+    if let Err(e) = login(
+        homepage,
+        overrides_from_args(matches).profile_name(),
+        show_token,
+    )
+    .await
+    {
+        eprintln!("Error during login:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn login(homepage: &str, profile_name: Option<&String>, show_token: bool) -> io::Result<()> {
+    let mut app_config = AppConfig::load_or_new()?;
+    let profile_name = profile_name
+        .cloned()
+        .unwrap_or_else(|| app_config.default_profile.clone());
+
+    // mp identifies itself with the URL of its loopback listener, which is on the same host as
+    // the redirect URI, rather than a web page it does not control.
+    let session = LoginSession::start(homepage, None, DEFAULT_SCOPE)
+        .await
+        .map_err(io::Error::other)?;
+
+    println!("Open the following URL in your browser to log in:");
+    println!();
+    println!("{}", session.authorization_url());
+    println!();
+    open_in_browser(session.authorization_url());
+    println!("Waiting for you to approve the login...");
+
+    let result = tokio::time::timeout(Duration::from_secs(LOGIN_TIMEOUT_SECS), session.finish())
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "The login timed out"))?
+        .map_err(io::Error::other)?;
+
+    // Keep the settings of an existing profile; only its endpoint and token change.
+    let mut profile = app_config
+        .profiles
+        .get(&profile_name)
+        .cloned()
+        .unwrap_or_else(|| {
+            Profile::new(
+                profile_name.clone(),
+                MicropubService::new(String::new(), String::new()),
+                DefaultBehavior::new(false, false, Vec::new()),
+            )
+        });
+    profile.service.api_url = result.micropub_endpoint;
+    profile.service.token_endpoint = Some(result.token_endpoint);
+
+    // A profile that reads its token from elsewhere keeps doing so, and the new token is not
+    // written to the configuration file. Token files are updated; commands and environment
+    // variables read from secret stores mp cannot write to, so the token is only shown on request.
+    let mut effective_profile = profile.clone();
+    effective_profile.service.auth_token = result.access_token.clone();
+    if !profile.token_source.is_configured() {
+        profile.service.auth_token = result.access_token;
+    } else if profile.token_source.write_token(&result.access_token)? {
+        println!(
+            "The new token was saved in token file '{}'.",
+            profile
+                .token_source
+                .token_file
+                .as_deref()
+                .unwrap_or_default()
+        );
+    } else {
+        eprintln!(
+            "Profile '{}' reads its token from {}. Update the secret it reads with the new token.",
+            profile_name,
+            profile.token_source.key()
+        );
+        if show_token {
+            println!("{}", result.access_token);
+        } else if user_confirms("Do you want to print the new token") {
+            eprintln!("{}", result.access_token);
+        }
+    }
+
+    if app_config.profiles.is_empty() {
+        app_config.default_profile = profile_name.clone();
+    }
+    app_config.set_profile(profile);
+    let app_config_path = app_config.save()?;
+
+    if let Err(e) = ServerConfigCache::refresh(&effective_profile).await {
+        eprintln!("Warning: could not query the server configuration:");
+        eprintln!("{}", e);
+    }
+
+    println!(
+        "Logged in as {}. Profile '{}' saved successfully in file '{}'.",
+        result.me, profile_name, app_config_path
+    );
+    if let Some(scope) = result.scope {
+        println!("Granted scopes: {}", scope);
+    }
+
+    Ok(())
+}
+
+/// Try to open the URL in the default browser. Failures are ignored, since the URL is also
+/// printed for the user to open manually.
+fn open_in_browser(url: &str) {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    let _ = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
@@ -0,0 +1,87 @@
+//! Handle the 'media' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::MicropubClient;
+use std::path::Path;
+
+use crate::cli::overrides_from_args;
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::profile::Profile;
+use crate::configuration::server_config_cache::ServerConfigCache;
+
+/// Handle the 'media' subcommand.
+pub async fn handle_media_subcommand(matches: &ArgMatches) {
+    if let Some(upload_matches) = matches.subcommand_matches("upload") {
+        handle_upload_subcommand(upload_matches).await;
+    }
+}
+
+/// Handle the 'media upload' subcommand.
+async fn handle_upload_subcommand(matches: &ArgMatches) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
+        eprintln!("Error loading configuration:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let path = matches
+        .get_one::<String>("path")
+        .expect("path is a required argument");
+
+    let client = MicropubClient::new(profile.service.clone()).unwrap_or_else(|e| {
+        eprintln!("Error uploading media:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    match upload_file(Path::new(path), &profile, &client).await {
+        Ok(url) => println!("{}", url),
+        Err(e) => {
+            eprintln!("Error uploading media:");
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Upload a local file to the service's media endpoint, or return the source unchanged if it
+/// is already a URL.
+pub async fn upload_or_use_url(
+    source: &str,
+    profile: &Profile,
+    client: &MicropubClient,
+) -> Result<String, String> {
+    if is_url(source) {
+        Ok(source.to_string())
+    } else {
+        upload_file(Path::new(source), profile, client).await
+    }
+}
+
+/// Upload a local file to the media endpoint advertised by the service.
+async fn upload_file(
+    path: &Path,
+    profile: &Profile,
+    client: &MicropubClient,
+) -> Result<String, String> {
+    if !path.is_file() {
+        return Err(format!("'{}' is not a file", path.display()));
+    }
+
+    let server_config = ServerConfigCache::load_or_refresh(profile)
+        .await
+        .map_err(|e| format!("Could not query the server configuration: {}", e))?;
+    let media_endpoint = server_config
+        .media_endpoint
+        .ok_or_else(|| "The server does not advertise a media endpoint".to_string())?;
+
+    client
+        .upload_media(path, &media_endpoint)
+        .await
+        .map_err(|e| format!("Could not upload '{}': {}", path.display(), e))
+}
+
+/// Check if a media source is a URL rather than a local path.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
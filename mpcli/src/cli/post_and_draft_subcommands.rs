@@ -1,77 +1,401 @@
 //! Handle the 'post' and 'draft' subcommands for the CLI application.
 
 use clap::ArgMatches;
-use mplib::{Post, PostStatus, publish_post};
+use mplib::{MicropubClient, Photo, Post, PostStatus, RequestFormat};
+use std::path::Path;
 use std::{fs, io, io::Read};
 
+use crate::cli::categories_subcommand::warn_about_new_categories;
+use crate::cli::editor::{compose_in_editor, save_for_recovery};
+use crate::cli::front_matter::{FrontMatter, split_front_matter};
+use crate::cli::media_subcommand::{is_url, upload_or_use_url};
+use crate::cli::overrides_from_args;
+use crate::cli::server_targets::{resolve_destination, resolve_syndication_targets};
 use crate::configuration::app_config::AppConfig;
+use crate::configuration::profile::Profile;
+use crate::outbox::{Outbox, QueueState, QueuedPost, new_idempotency_key};
 
-/// Handle the 'post' or 'draft' subcommand.
-pub async fn handle_post_or_draft_subcommand(matches: &ArgMatches) {
-    let app_config = AppConfig::load().unwrap_or_else(|e| {
+/// Handle the 'post' or 'draft' subcommand, which create posts with the given status.
+pub async fn handle_post_or_draft_subcommand(matches: &ArgMatches, status: PostStatus) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
         eprintln!("Error loading configuration:");
         eprintln!("{}", e);
         std::process::exit(1);
     });
 
-    let status = if matches.subcommand_name() == Some("draft") {
-        PostStatus::Draft
-    } else {
-        PostStatus::Published
-    };
-
     let post_or_draft = match status {
         PostStatus::Published => "post",
         PostStatus::Draft => "draft",
     };
 
-    let extract_title =
-        app_config.default_behavior.extract_title || matches.get_flag("extract-title");
+    let extract_title = profile.default_behavior.extract_title || matches.get_flag("extract-title");
+    let queue = matches.get_flag("queue");
+
+    // Text composed in the editor is saved for recovery if it cannot be published.
+    let composed = if uses_editor(matches, &profile) {
+        match compose_in_editor(&editor_template(matches)) {
+            Ok(composed) => Some(composed),
+            Err(e) => {
+                eprintln!("Error composing {}:", post_or_draft);
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let exit_with_error = |message: &str, e: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}", message);
+        eprintln!("{}", e);
+        if let Some(composed) = &composed {
+            match save_for_recovery(composed) {
+                Ok(path) => eprintln!("Your text was saved in '{}'.", path.display()),
+                Err(e) => eprintln!("Your text could not be saved for recovery: {}", e),
+            }
+        }
+        std::process::exit(1);
+    };
+
+    let (mut post, front_matter) =
+        post_from_args(matches, status, extract_title, composed.as_deref())
+            .unwrap_or_else(|e| exit_with_error(&format!("Error creating {}:", post_or_draft), &e));
 
-    let post = post_from_args(matches, status, extract_title).unwrap();
+    // Form encoding cannot mark content as HTML, so it would be published as escaped text.
+    if post.html && profile.service.request_format == RequestFormat::Form {
+        exit_with_error(
+            &format!("Error creating {}:", post_or_draft),
+            &"HTML content can only be sent with the JSON request format. Run 'mp config set \
+              service.request_format json' to use it.",
+        );
+    }
 
-    if post.is_empty() {
-        eprintln!("Error: {} content cannot be empty", post_or_draft);
+    if post.is_empty() && !has_media_args(matches) && front_matter.photos.is_empty() {
+        if composed.is_some() {
+            eprintln!("The {} is empty. Nothing was published.", post_or_draft);
+        } else {
+            eprintln!("Error: {} content cannot be empty", post_or_draft);
+        }
         std::process::exit(1);
     }
 
-    let quiet = app_config.default_behavior.quiet || matches.get_flag("quiet");
+    let default_targets = if front_matter.syndicate_to.is_empty() {
+        &profile.default_behavior.syndicate_to
+    } else {
+        &front_matter.syndicate_to
+    };
+    let syndication_targets = syndication_targets_from_args(matches, default_targets);
+    post.syndicate_to = resolve_syndication_targets(&syndication_targets, &profile)
+        .await
+        .unwrap_or_else(|e| exit_with_error("Error resolving syndication targets:", &e));
+
+    let destination = matches
+        .get_one::<String>("destination")
+        .or(profile.default_behavior.destination.as_ref());
+    if let Some(destination) = destination {
+        post.destination = Some(
+            resolve_destination(destination, &profile)
+                .await
+                .unwrap_or_else(|e| exit_with_error("Error resolving destination:", &e)),
+        );
+    }
+
+    // The media files and the post are sent with the same client, so its connection is reused.
+    let client = MicropubClient::new(profile.service.clone())
+        .unwrap_or_else(|e| exit_with_error(&format!("Error publishing {}:", post_or_draft), &e));
 
-    match publish_post(post, &app_config.service).await {
+    // Local media files of queued posts are uploaded when the outbox is flushed.
+    let upload = if queue { None } else { Some(&client) };
+    if let Err(e) =
+        attach_media_from_args(&mut post, matches, &profile, &front_matter, upload).await
+    {
+        exit_with_error("Error attaching media:", &e);
+    }
+
+    let quiet = profile.default_behavior.quiet || matches.get_flag("quiet");
+    let idempotency_key = new_idempotency_key();
+
+    if queue {
+        let entry = QueuedPost::new(post, &profile, idempotency_key, QueueState::Pending);
+        match Outbox::open().and_then(|outbox| outbox.add(entry)) {
+            Ok(id) => {
+                if !quiet {
+                    println!(
+                        "The {} was queued with ID {}. Run 'mp queue flush' to publish it.",
+                        post_or_draft, id
+                    );
+                }
+                return;
+            }
+            Err(e) => exit_with_error(&format!("Error queuing {}:", post_or_draft), &e),
+        }
+    }
+
+    warn_about_new_categories(&post.categories, &profile).await;
+
+    match client
+        .create_with_idempotency_key(post.clone(), &idempotency_key)
+        .await
+    {
         Ok(result) => {
             if quiet {
                 return;
             }
             println!("{}", result.as_string());
         }
-        Err(e) => {
-            eprintln!("Error publishing {}:", post_or_draft);
-            eprintln!("{}", e);
-            std::process::exit(1);
+        // Posts that fail for reasons that may go away are kept in the outbox, with the same
+        // idempotency key, so they can be published later. Posts that may have reached the
+        // server are not retried automatically, and are marked uncertain instead.
+        Err(e) if e.is_transient() || e.is_delivery_uncertain() => {
+            let state = if e.is_delivery_uncertain() {
+                QueueState::Uncertain
+            } else {
+                QueueState::Pending
+            };
+            let mut entry = QueuedPost::new(post, &profile, idempotency_key, state);
+            entry.attempts = 1;
+            entry.last_error = Some(e.to_string());
+            match Outbox::open().and_then(|outbox| outbox.add(entry)) {
+                Ok(id) => {
+                    eprintln!("Error publishing {}:", post_or_draft);
+                    eprintln!("{}", e);
+                    eprintln!(
+                        "The {} was saved in the outbox with ID {}. Run 'mp queue flush' to publish it.",
+                        post_or_draft, id
+                    );
+                    std::process::exit(1);
+                }
+                Err(_) => exit_with_error(&format!("Error publishing {}:", post_or_draft), &e),
+            }
         }
+        Err(e) => exit_with_error(&format!("Error publishing {}:", post_or_draft), &e),
     }
 }
 
-/// Construct a Post from CLI arguments.
+/// Check if the post should be composed in the editor: when asked to with `--edit`, or by
+/// default if the profile says so and no content is given on the command line.
+fn uses_editor(matches: &ArgMatches, profile: &Profile) -> bool {
+    let has_content_args = matches.get_one::<String>("content").is_some()
+        || matches.get_one::<String>("file").is_some()
+        || matches.get_flag("stdin");
+    matches.get_flag("edit") || (profile.default_behavior.compose_in_editor && !has_content_args)
+}
+
+/// Create the text the editor opens with: front matter holding the title and categories given
+/// on the command line, followed by an empty body.
+fn editor_template(matches: &ArgMatches) -> String {
+    let title = matches
+        .get_one::<String>("title")
+        .cloned()
+        .unwrap_or_default();
+    let categories: Vec<String> = matches
+        .get_many::<String>("category")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+
+    format!(
+        "+++\ntitle = {}\ncategories = {}\n+++\n\n",
+        toml::Value::from(title),
+        toml::Value::from(categories)
+    )
+}
+
+/// Construct a Post from CLI arguments and the front matter of its content, and return it
+/// together with the front matter. Only files and text composed in the editor may start with
+/// front matter; other content is sent as given. The title and categories given on the command
+/// line take precedence over the front matter, except for text composed in the editor, whose
+/// front matter was prefilled with them.
 fn post_from_args(
     matches: &ArgMatches,
     status: PostStatus,
     extract_title_default: bool,
-) -> Result<Post, String> {
-    let body = get_post_body_from_cli_args(matches)
-        .map_err(|e| format!("Error reading content: {}", e))?;
-    let title = matches.get_one::<String>("title").cloned();
+    composed: Option<&str>,
+) -> Result<(Post, FrontMatter), String> {
+    let has_front_matter = composed.is_some() || reads_content_from_file(matches);
+    let content = match composed {
+        Some(composed) => composed.to_string(),
+        None => match get_post_body_from_cli_args(matches) {
+            Ok(content) => content,
+            // Posts consisting only of attachments need no content.
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput && has_media_args(matches) => {
+                String::new()
+            }
+            Err(e) => return Err(format!("Error reading content: {}", e)),
+        },
+    };
+    let (front_matter, body) = if has_front_matter {
+        split_front_matter(&content).map_err(|e| e.to_string())?
+    } else {
+        (FrontMatter::default(), content)
+    };
+
+    let flags_take_precedence = composed.is_none();
+    let title = matches
+        .get_one::<String>("title")
+        .filter(|_| flags_take_precedence)
+        .or(front_matter.title.as_ref())
+        .filter(|title| !title.trim().is_empty())
+        .cloned();
+    let category_args: Vec<String> = matches
+        .get_many::<String>("category")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let categories = if flags_take_precedence && !category_args.is_empty() {
+        category_args
+    } else {
+        front_matter.categories.clone()
+    };
+    let status = if front_matter.draft == Some(true) {
+        PostStatus::Draft
+    } else {
+        status
+    };
     let extract_title = title.is_none() && extract_title_default;
 
-    if extract_title {
-        Ok(Post::from_body_with_title_extraction(body, status))
-    } else if title.is_some() {
-        Ok(Post::from_body_and_title(body, title.unwrap(), status))
+    let mut post = if extract_title {
+        Post::from_body_with_title_extraction(body, status)
+    } else if let Some(title) = title {
+        Post::from_body_and_title(body, title, status)
+    } else {
+        Post::from_body(body, status)
+    };
+    post.html = matches.get_flag("html");
+    post.categories = categories;
+    post.published = front_matter.published.clone();
+    post.slug = front_matter.slug.clone();
+    post.in_reply_to = front_matter.in_reply_to.clone();
+
+    Ok((post, front_matter))
+}
+
+/// Get the syndication targets given on the command line, falling back to the default targets
+/// unless syndication was disabled with `--no-syndicate`.
+fn syndication_targets_from_args(matches: &ArgMatches, default_targets: &[String]) -> Vec<String> {
+    if matches.get_flag("no-syndicate") {
+        return Vec::new();
+    }
+    match matches.get_many::<String>("syndicate-to") {
+        Some(targets) => targets.cloned().collect(),
+        None => default_targets.to_vec(),
+    }
+}
+
+/// Check if any photo, video, or audio attachments were given on the command line.
+fn has_media_args(matches: &ArgMatches) -> bool {
+    ["photo", "video", "audio"]
+        .iter()
+        .any(|id| matches.get_many::<String>(id).is_some())
+}
+
+/// Upload the photos, videos, and audio files given on the command line and attach them to the
+/// post. Arguments that are already URLs are attached without uploading them. Photos given in
+/// the front matter are attached unless photos are given on the command line.
+///
+/// Without a client to `upload` them with, local files are attached by their absolute path, to
+/// be uploaded later.
+async fn attach_media_from_args(
+    post: &mut Post,
+    matches: &ArgMatches,
+    profile: &Profile,
+    front_matter: &FrontMatter,
+    upload: Option<&MicropubClient>,
+) -> Result<(), String> {
+    let attach = async |source: &str| {
+        if is_url(source) {
+            Ok(source.to_string())
+        } else if let Some(client) = upload {
+            upload_or_use_url(source, profile, client).await
+        } else {
+            fs::canonicalize(source)
+                .map(|path| path.to_string_lossy().into_owned())
+                .map_err(|e| format!("Could not find '{}': {}", source, e))
+        }
+    };
+
+    let photos: Vec<(String, Option<String>)> = match matches.get_many::<String>("photo") {
+        Some(photo_args) => photo_args
+            .map(|photo_arg| {
+                let (source, alt) = parse_photo_arg(photo_arg);
+                (source.to_string(), alt.map(str::to_string))
+            })
+            .collect(),
+        None => front_matter
+            .photos
+            .iter()
+            .map(|photo| {
+                let source = resolve_front_matter_path(&photo.source, matches);
+                (source, photo.alt.clone())
+            })
+            .collect(),
+    };
+    for (source, alt) in photos {
+        let url = attach(&source).await?;
+        post.photos.push(Photo::new(url, alt));
+    }
+    for video in matches.get_many::<String>("video").into_iter().flatten() {
+        post.videos.push(attach(video).await?);
+    }
+    for audio in matches.get_many::<String>("audio").into_iter().flatten() {
+        post.audio.push(attach(audio).await?);
+    }
+    Ok(())
+}
+
+/// Resolve a relative path given in the front matter of a `--file` against the directory of the
+/// file, so that photos next to a post are found wherever `mp` runs.
+fn resolve_front_matter_path(source: &str, matches: &ArgMatches) -> String {
+    match matches.get_one::<String>("file") {
+        Some(file) if !is_url(source) && Path::new(source).is_relative() => Path::new(file)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(source)
+            .to_string_lossy()
+            .into_owned(),
+        _ => source.to_string(),
+    }
+}
+
+/// Split a `--photo` argument of the form `PATH[:ALT]` into its source and optional alt text.
+///
+/// A colon that belongs to the source itself (a URL scheme or port, a Windows drive letter, or
+/// an existing file name) does not start the alt text.
+fn parse_photo_arg(arg: &str) -> (&str, Option<&str>) {
+    if Path::new(arg).exists() {
+        return (arg, None);
+    }
+
+    // Find where the alt text may start: after the host of a URL, or after a drive letter.
+    let search_start = if is_url(arg) {
+        let after_scheme = arg.find("://").map_or(0, |i| i + 3);
+        arg[after_scheme..]
+            .find('/')
+            .map_or(arg.len(), |i| after_scheme + i)
+    } else if arg.len() >= 2 && arg.as_bytes()[1] == b':' && arg.as_bytes()[0].is_ascii_alphabetic()
+    {
+        2
     } else {
-        Ok(Post::from_body(body, status))
+        0
+    };
+
+    match arg[search_start..].find(':') {
+        Some(i) => {
+            let (source, alt) = arg.split_at(search_start + i);
+            let alt = alt[1..].trim();
+            (source, (!alt.is_empty()).then_some(alt))
+        }
+        None => (arg, None),
     }
 }
 
+/// Check if the content is read from the file given with `--file`, rather than from stdin or
+/// the command line.
+fn reads_content_from_file(matches: &ArgMatches) -> bool {
+    !matches.get_flag("stdin") && matches.get_one::<String>("file").is_some()
+}
+
 /// Retrieve the post body from CLI arguments.
 fn get_post_body_from_cli_args(matches: &ArgMatches) -> Result<String, std::io::Error> {
     if matches.get_flag("stdin") {
@@ -89,3 +413,41 @@ fn get_post_body_from_cli_args(matches: &ArgMatches) -> Result<String, std::io::
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_photo_arg_without_alt() {
+        assert_eq!(parse_photo_arg("cat.jpg"), ("cat.jpg", None));
+    }
+
+    #[test]
+    fn test_parse_photo_arg_with_alt() {
+        assert_eq!(
+            parse_photo_arg("photos/cat.jpg:A cat: asleep"),
+            ("photos/cat.jpg", Some("A cat: asleep"))
+        );
+    }
+
+    #[test]
+    fn test_parse_photo_arg_url_with_port_and_alt() {
+        assert_eq!(
+            parse_photo_arg("https://example.com:8080/cat.jpg:A cat"),
+            ("https://example.com:8080/cat.jpg", Some("A cat"))
+        );
+        assert_eq!(
+            parse_photo_arg("https://example.com/cat.jpg"),
+            ("https://example.com/cat.jpg", None)
+        );
+    }
+
+    #[test]
+    fn test_parse_photo_arg_windows_path() {
+        assert_eq!(
+            parse_photo_arg(r"C:\photos\cat.jpg:A cat"),
+            (r"C:\photos\cat.jpg", Some("A cat"))
+        );
+    }
+}
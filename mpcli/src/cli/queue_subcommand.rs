@@ -0,0 +1,267 @@
+//! Handle the 'queue' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::{MicropubClient, Post, PostResult};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cli::media_subcommand::{is_url, upload_or_use_url};
+use crate::cli::overrides_from_args;
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::overrides::{Origin, Overrides, Setting};
+use crate::configuration::profile::Profile;
+use crate::outbox::{Outbox, QueueState, QueuedPost};
+
+/// Handle the 'queue' subcommand.
+pub async fn handle_queue_subcommand(matches: &ArgMatches) {
+    let outbox =
+        Outbox::open().unwrap_or_else(|e| exit_with_error("Error opening the outbox:", &e));
+
+    if matches.subcommand_matches("list").is_some() {
+        handle_list_subcommand(&outbox);
+    } else if let Some(flush_matches) = matches.subcommand_matches("flush") {
+        handle_flush_subcommand(&outbox, flush_matches).await;
+    } else if let Some(drop_matches) = matches.subcommand_matches("drop") {
+        handle_drop_subcommand(&outbox, drop_matches);
+    } else if let Some(show_matches) = matches.subcommand_matches("show") {
+        handle_show_subcommand(&outbox, show_matches);
+    }
+}
+
+fn exit_with_error(message: &str, e: &dyn Display) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", e);
+    std::process::exit(1);
+}
+
+/// Handle the 'queue list' subcommand.
+fn handle_list_subcommand(outbox: &Outbox) {
+    let entries = outbox
+        .entries()
+        .unwrap_or_else(|e| exit_with_error("Error reading the outbox:", &e));
+    if entries.is_empty() {
+        println!("The outbox is empty.");
+        return;
+    }
+
+    println!(
+        "{:<5} {:<10} {:<16} {:<12} POST",
+        "ID", "STATE", "QUEUED", "PROFILE"
+    );
+    for entry in entries {
+        println!(
+            "{:<5} {:<10} {:<16} {:<12} {}",
+            entry.id,
+            entry.state.as_str(),
+            describe_age(entry.queued_at),
+            entry.profile,
+            entry.summary()
+        );
+        if let Some(last_error) = &entry.last_error {
+            println!("      Last error: {}", last_error);
+        }
+    }
+}
+
+/// Handle the 'queue show' subcommand.
+fn handle_show_subcommand(outbox: &Outbox, matches: &ArgMatches) {
+    let id = *matches
+        .get_one::<u32>("id")
+        .expect("id is a required argument");
+    let entry = outbox
+        .get(id)
+        .unwrap_or_else(|e| exit_with_error("Error reading the outbox:", &e));
+
+    println!("ID:          {}", entry.id);
+    println!("Profile:     {} ({})", entry.profile, entry.api_url);
+    println!("State:       {}", entry.state.as_str());
+    println!("Queued:      {}", describe_age(entry.queued_at));
+    println!("Attempts:    {}", entry.attempts);
+    if let Some(last_error) = &entry.last_error {
+        println!("Last error:  {}", last_error);
+    }
+
+    // Show the properties the post is published with, followed by its content.
+    let properties = entry.post.to_properties().to_json_map();
+    for (name, value) in properties.iter().filter(|(name, _)| *name != "content") {
+        println!("{:<12} {}", format!("{}:", name), value);
+    }
+    println!();
+    println!("{}", entry.post.body);
+}
+
+/// Handle the 'queue drop' subcommand.
+fn handle_drop_subcommand(outbox: &Outbox, matches: &ArgMatches) {
+    let id = *matches
+        .get_one::<u32>("id")
+        .expect("id is a required argument");
+    match outbox.remove(id) {
+        Ok(()) => println!("Queued post {} was dropped.", id),
+        Err(e) => exit_with_error("Error dropping the queued post:", &e),
+    }
+}
+
+/// Handle the 'queue flush' subcommand: publish the queued posts, oldest first, and remove the
+/// ones that were published from the outbox.
+///
+/// Posts that may already have been published are skipped unless `--retry-uncertain` is given,
+/// in which case they are sent again with their original idempotency key.
+async fn handle_flush_subcommand(outbox: &Outbox, matches: &ArgMatches) {
+    let _lock = outbox
+        .lock()
+        .unwrap_or_else(|e| exit_with_error("Error flushing the outbox:", &e));
+    let overrides = overrides_from_args(matches);
+    let entries: Vec<QueuedPost> = outbox
+        .entries()
+        .unwrap_or_else(|e| exit_with_error("Error reading the outbox:", &e))
+        .into_iter()
+        // With --profile, only the posts queued for that profile are published.
+        .filter(|entry| {
+            overrides
+                .profile_name()
+                .is_none_or(|name| *name == entry.profile)
+        })
+        .collect();
+    if entries.is_empty() {
+        println!("There are no queued posts to publish.");
+        return;
+    }
+
+    let retry_uncertain = matches.get_flag("retry-uncertain");
+    let mut clients = HashMap::new();
+    let mut failures = 0;
+    for mut entry in entries {
+        if entry.state.may_be_published() && !retry_uncertain {
+            eprintln!(
+                "Skipped queued post {}: it may already have been published. Check your site, then run 'mp queue flush --retry-uncertain' to send it again or 'mp queue drop {}' to discard it.",
+                entry.id, entry.id
+            );
+            failures += 1;
+            continue;
+        }
+
+        match flush_entry(outbox, &mut entry, &overrides, &mut clients).await {
+            Ok(result) => {
+                if let Err(e) = outbox.remove(entry.id) {
+                    eprintln!(
+                        "Warning: queued post {} was published but could not be removed from the outbox: {}",
+                        entry.id, e
+                    );
+                }
+                println!("Queued post {}: {}", entry.id, result.as_string());
+            }
+            Err(e) => {
+                eprintln!("Queued post {} was not published: {}", entry.id, e);
+                entry.last_error = Some(e);
+                if let Err(e) = outbox.save(&entry) {
+                    eprintln!("Warning: the outbox could not be updated: {}", e);
+                }
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Publish a queued post with the profile it was queued for.
+///
+/// The post is marked as being sent before its request is sent. If the request fails, the post
+/// is marked as pending again, unless the server may have processed it.
+///
+/// Each profile is loaded once, and its client is reused for all the posts queued for it.
+async fn flush_entry(
+    outbox: &Outbox,
+    entry: &mut QueuedPost,
+    overrides: &Overrides,
+    clients: &mut HashMap<String, (Profile, MicropubClient)>,
+) -> Result<PostResult, String> {
+    if !clients.contains_key(&entry.profile) {
+        let overrides = Overrides {
+            profile: Some(Setting {
+                value: entry.profile.clone(),
+                origin: Origin::ConfigFile,
+            }),
+            ..overrides.clone()
+        };
+        let profile = AppConfig::load_profile(&overrides)
+            .map_err(|e| format!("Could not load profile '{}': {}", entry.profile, e))?;
+        let client = MicropubClient::new(profile.service.clone()).map_err(|e| e.to_string())?;
+        clients.insert(entry.profile.clone(), (profile, client));
+    }
+    let (profile, client) = &clients[&entry.profile];
+    if profile.service.api_url != entry.api_url {
+        return Err(format!(
+            "It was queued for '{}', but profile '{}' now publishes to '{}'.",
+            entry.api_url, entry.profile, profile.service.api_url
+        ));
+    }
+
+    // Local files of posts queued with --queue are uploaded when the post is published. The
+    // uploaded URLs are saved right away so the files are not uploaded twice.
+    if upload_local_media(&mut entry.post, profile, client).await? {
+        outbox.save(entry).map_err(|e| e.to_string())?;
+    }
+
+    entry.state = QueueState::Sending;
+    entry.attempts += 1;
+    outbox.save(entry).map_err(|e| e.to_string())?;
+
+    match client
+        .create_with_idempotency_key(entry.post.clone(), &entry.idempotency_key)
+        .await
+    {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            entry.state = if e.is_delivery_uncertain() {
+                QueueState::Uncertain
+            } else {
+                QueueState::Pending
+            };
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Upload the photos, videos, and audio files of a post that are local files, and replace them
+/// with their URLs. Returns whether any file was uploaded.
+async fn upload_local_media(
+    post: &mut Post,
+    profile: &Profile,
+    client: &MicropubClient,
+) -> Result<bool, String> {
+    let mut uploaded = false;
+    let sources = post
+        .photos
+        .iter_mut()
+        .map(|photo| &mut photo.url)
+        .chain(post.videos.iter_mut())
+        .chain(post.audio.iter_mut());
+    for source in sources {
+        if !is_url(source) {
+            *source = upload_or_use_url(source, profile, client).await?;
+            uploaded = true;
+        }
+    }
+    Ok(uploaded)
+}
+
+/// Describe how long ago a post was queued, such as "5 minutes ago".
+fn describe_age(queued_at: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let seconds = now.saturating_sub(queued_at);
+
+    let (count, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
@@ -0,0 +1,191 @@
+//! Resolve syndication targets and destinations given on the command line.
+
+use mplib::{Destination, SyndicationTarget, query_syndication_targets};
+
+use crate::configuration::profile::Profile;
+use crate::configuration::server_config_cache::ServerConfigCache;
+
+/// A target advertised by the server that can be referred to by UID or name.
+trait NamedTarget {
+    fn uid(&self) -> &str;
+    fn name(&self) -> &str;
+}
+
+impl NamedTarget for SyndicationTarget {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl NamedTarget for Destination {
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Resolve syndication targets given by UID or name into UIDs.
+///
+/// Targets are looked up in the cached server configuration first. If any target cannot be
+/// found there, the server is queried for its current targets and the cache is updated.
+pub async fn resolve_syndication_targets(
+    targets: &[String],
+    profile: &Profile,
+) -> Result<Vec<String>, String> {
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut cached_config = ServerConfigCache::load(profile).ok().flatten();
+    let cached_targets = cached_config
+        .as_ref()
+        .map(|config| config.syndicate_to.as_slice())
+        .unwrap_or_default();
+
+    if let Some(uids) = resolve_all(targets, cached_targets) {
+        return Ok(uids);
+    }
+
+    let server_targets = query_syndication_targets(&profile.service)
+        .await
+        .map_err(|e| format!("Could not query syndication targets: {}", e))?;
+
+    if let Some(config) = cached_config.as_mut() {
+        config.syndicate_to = server_targets.clone();
+        // Failing to update the cache does not prevent publishing.
+        let _ = ServerConfigCache::save(profile, config);
+    }
+
+    targets
+        .iter()
+        .map(|target| {
+            resolve_target(target, &server_targets).ok_or_else(|| {
+                format!(
+                    "Unknown syndication target '{}'. Available targets: {}",
+                    target,
+                    describe_targets(&server_targets)
+                )
+            })
+        })
+        .collect()
+}
+
+/// Resolve a destination given by UID or name into its UID.
+///
+/// The destination is looked up in the cached server configuration first. If it cannot be
+/// found there, the server configuration is queried again and cached.
+pub async fn resolve_destination(destination: &str, profile: &Profile) -> Result<String, String> {
+    let cached_destinations = ServerConfigCache::load(profile)
+        .ok()
+        .flatten()
+        .map(|config| config.destinations)
+        .unwrap_or_default();
+
+    if let Some(uid) = resolve_target(destination, &cached_destinations) {
+        return Ok(uid);
+    }
+
+    let server_destinations = ServerConfigCache::refresh(profile)
+        .await
+        .map_err(|e| format!("Could not query the server configuration: {}", e))?
+        .destinations;
+
+    resolve_target(destination, &server_destinations).ok_or_else(|| {
+        format!(
+            "Unknown destination '{}'. Available destinations: {}",
+            destination,
+            describe_targets(&server_destinations)
+        )
+    })
+}
+
+/// Resolve every target, or return None if any of them is unknown.
+fn resolve_all<T: NamedTarget>(targets: &[String], known_targets: &[T]) -> Option<Vec<String>> {
+    targets
+        .iter()
+        .map(|target| resolve_target(target, known_targets))
+        .collect()
+}
+
+/// Resolve a target given by UID or (case-insensitive) name into its UID.
+fn resolve_target<T: NamedTarget>(target: &str, known_targets: &[T]) -> Option<String> {
+    known_targets
+        .iter()
+        .find(|known| known.uid() == target)
+        .or_else(|| {
+            known_targets
+                .iter()
+                .find(|known| known.name().eq_ignore_ascii_case(target))
+        })
+        .map(|known| known.uid().to_string())
+}
+
+/// List the known targets as a human-readable string.
+fn describe_targets<T: NamedTarget>(known_targets: &[T]) -> String {
+    if known_targets.is_empty() {
+        return "(none)".to_string();
+    }
+    known_targets
+        .iter()
+        .map(|known| format!("{} ({})", known.name(), known.uid()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known_targets() -> Vec<SyndicationTarget> {
+        vec![
+            SyndicationTarget {
+                uid: "https://mastodon.example/@me".to_string(),
+                name: "Mastodon".to_string(),
+            },
+            SyndicationTarget {
+                uid: "https://bsky.example/me".to_string(),
+                name: "Bluesky".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_resolve_target_by_uid_and_name() {
+        let known = known_targets();
+        assert_eq!(
+            resolve_target("https://bsky.example/me", &known),
+            Some("https://bsky.example/me".to_string())
+        );
+        assert_eq!(
+            resolve_target("mastodon", &known),
+            Some("https://mastodon.example/@me".to_string())
+        );
+        assert_eq!(resolve_target("Threads", &known), None);
+    }
+
+    #[test]
+    fn test_resolve_all_fails_on_unknown_target() {
+        let known = known_targets();
+        let targets = vec!["Mastodon".to_string(), "Threads".to_string()];
+        assert_eq!(resolve_all(&targets, &known), None);
+    }
+
+    #[test]
+    fn test_resolve_destination_by_name() {
+        let known = vec![Destination {
+            uid: "https://team.example.com/".to_string(),
+            name: "Team blog".to_string(),
+        }];
+        assert_eq!(
+            resolve_target("team blog", &known),
+            Some("https://team.example.com/".to_string())
+        );
+    }
+}
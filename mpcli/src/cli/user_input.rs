@@ -0,0 +1,21 @@
+//! Prompt the user for input on the command line.
+
+use rustyline::DefaultEditor;
+
+/// Prompt the user for a line of input and return it without surrounding whitespace.
+/// Returns an empty string if no input could be read.
+pub fn get_user_input(prompt: &str) -> String {
+    let prompt = format!("{}: ", prompt);
+    let mut rl = DefaultEditor::new().expect("Failed to create readline editor");
+    let readline = rl.readline(&prompt);
+
+    match readline {
+        Ok(line) => line.trim().to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Ask the user a yes/no question that defaults to no.
+pub fn user_confirms(prompt: &str) -> bool {
+    get_user_input(&format!("{} (y/N)?", prompt)).eq_ignore_ascii_case("y")
+}
@@ -0,0 +1,61 @@
+//! Handle the 'whoami' subcommand for the CLI application.
+
+use clap::ArgMatches;
+use mplib::{DEFAULT_SCOPE, TokenInfo, verify_token};
+
+use crate::cli::overrides_from_args;
+use crate::configuration::app_config::AppConfig;
+
+/// Handle the 'whoami' subcommand.
+pub async fn handle_whoami_subcommand(matches: &ArgMatches) {
+    let profile = AppConfig::load_profile(&overrides_from_args(matches)).unwrap_or_else(|e| {
+        eprintln!("Error loading configuration:");
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    match verify_token(&profile.service).await {
+        Ok(token_info) => {
+            println!(
+                "{}",
+                token_info_as_string(&token_info, &profile.service.api_url)
+            );
+            warn_about_missing_scopes(&token_info);
+        }
+        Err(e) => {
+            eprintln!("Error verifying token:");
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Warn if the token lacks any of the scopes mp needs to create, update, and delete posts and
+/// upload media.
+pub fn warn_about_missing_scopes(token_info: &TokenInfo) {
+    let wanted: Vec<&str> = DEFAULT_SCOPE.split_whitespace().collect();
+    let missing = token_info.missing_scopes(&wanted);
+    if !missing.is_empty() {
+        eprintln!(
+            "Warning: the token lacks the following scopes, so some commands will fail: {}",
+            missing.join(", ")
+        );
+    }
+}
+
+/// Format what is known about a token as a human-readable string.
+fn token_info_as_string(token_info: &TokenInfo, api_url: &str) -> String {
+    let unknown = "(not reported)".to_string();
+    let scopes = token_info
+        .scopes
+        .as_ref()
+        .map_or(unknown.clone(), |scopes| scopes.join(" "));
+
+    format!(
+        "The token is valid.\n\nMe:        {}\nClient ID: {}\nScopes:    {}\nEndpoint:  {}",
+        token_info.me.as_ref().unwrap_or(&unknown),
+        token_info.client_id.as_ref().unwrap_or(&unknown),
+        scopes,
+        api_url
+    )
+}
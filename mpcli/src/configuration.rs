@@ -1,2 +1,9 @@
 pub(crate) mod app_config;
+pub(crate) mod config_error;
+pub(crate) mod config_key;
 pub(crate) mod default_behavior;
+pub(crate) mod migration;
+pub(crate) mod overrides;
+pub(crate) mod profile;
+pub(crate) mod server_config_cache;
+pub(crate) mod token_source;
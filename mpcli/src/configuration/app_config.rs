@@ -1,27 +1,66 @@
 use directories::ProjectDirs;
 use mplib::MicropubService;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::sync::OnceLock;
+use toml::{Table, Value};
 
+use crate::configuration::config_error::ConfigError;
+use crate::configuration::config_key::unknown_keys;
 use crate::configuration::default_behavior::DefaultBehavior;
+use crate::configuration::migration::{CURRENT_CONFIG_VERSION, migrate};
+use crate::configuration::overrides::{EffectiveProfile, Origin, Overrides, Setting};
+use crate::configuration::profile::Profile;
+
+/// The name of the profile created when no other name is given.
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// The configuration file given with `--config` or `MP_CONFIG`, if any.
+static CONFIG_FILE_PATH: OnceLock<Setting> = OnceLock::new();
 
 #[derive(Serialize, Deserialize)]
 pub struct AppConfig {
-    pub service: MicropubService,
-    pub default_behavior: DefaultBehavior,
+    /// The version of the layout of the configuration file. See the `migration` module.
+    pub config_version: u32,
+    pub default_profile: String,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// The version of the configuration file as it was loaded, before it was upgraded.
+    #[serde(skip)]
+    loaded_version: Option<u32>,
 }
 
 impl AppConfig {
-    pub fn new(service: MicropubService, default_behavior: DefaultBehavior) -> Self {
+    pub fn new() -> Self {
         AppConfig {
-            service,
-            default_behavior,
+            config_version: CURRENT_CONFIG_VERSION,
+            default_profile: DEFAULT_PROFILE_NAME.to_string(),
+            profiles: BTreeMap::new(),
+            loaded_version: None,
         }
     }
 
+    /// Use the given configuration file instead of the one in the standard configuration
+    /// directory. Only the first call has an effect.
+    pub fn set_config_file_path(path: Setting) {
+        let _ = CONFIG_FILE_PATH.set(path);
+    }
+
+    /// Get where the path of the configuration file comes from.
+    pub fn config_file_path_origin() -> Origin {
+        CONFIG_FILE_PATH
+            .get()
+            .map_or(Origin::Default, |path| path.origin.clone())
+    }
+
     pub fn get_config_file_path() -> io::Result<String> {
+        if let Some(path) = CONFIG_FILE_PATH.get() {
+            return Ok(path.value.clone());
+        }
+
         let proj_dirs = ProjectDirs::from("", "", "mp").ok_or_else(|| {
             io::Error::new(
                 io::ErrorKind::NotFound,
@@ -50,29 +89,340 @@ impl AppConfig {
             ));
         }
 
-        let config_content = fs::read_to_string(config_path)?;
-        let app_config: AppConfig = toml::from_str(&config_content).map_err(|e| {
+        let config_content = fs::read_to_string(&config_path)?;
+        let mut app_config = AppConfig::parse(&config_content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        for (name, profile) in app_config.profiles.iter_mut() {
+            profile.name = name.clone();
+        }
+
+        if app_config.has_inline_tokens() {
+            warn_if_readable_by_others(Path::new(&config_path));
+        }
+
+        Ok(app_config)
+    }
+
+    /// Load the configuration file, or start a new configuration if none exists yet.
+    pub fn load_or_new() -> io::Result<Self> {
+        if AppConfig::config_file_exists() {
+            AppConfig::load()
+        } else {
+            Ok(AppConfig::new())
+        }
+    }
+
+    /// Load the profile selected by the overrides, or the default profile, with the overrides
+    /// applied.
+    pub fn load_profile(overrides: &Overrides) -> io::Result<Profile> {
+        AppConfig::load_effective_profile(overrides).map(|effective| effective.profile)
+    }
+
+    /// Load the profile selected by the overrides, or the default profile, with the overrides
+    /// applied, and report where each of its settings comes from.
+    ///
+    /// If both the API URL and the token are overridden, the configuration file and the profile
+    /// need not exist, which lets `mp` run without any configuration file.
+    pub fn load_effective_profile(overrides: &Overrides) -> io::Result<EffectiveProfile> {
+        let standalone = overrides.api_url.is_some() && overrides.token.is_some();
+        let app_config = if standalone {
+            AppConfig::load_or_new()?
+        } else {
+            AppConfig::load()?
+        };
+
+        let (name, profile_origin) = match &overrides.profile {
+            Some(setting) => (setting.value.clone(), setting.origin.clone()),
+            None if app_config.profiles.is_empty() => {
+                (app_config.default_profile.clone(), Origin::Default)
+            }
+            None => (app_config.default_profile.clone(), Origin::ConfigFile),
+        };
+
+        let mut profile = match app_config.stored_profile(&name) {
+            Ok(profile) => profile,
+            Err(_) if standalone => Profile::new(
+                name,
+                MicropubService::new(String::new(), String::new()),
+                DefaultBehavior::new(false, false, Vec::new()),
+            ),
+            Err(e) => return Err(e),
+        };
+
+        let api_url_origin = match &overrides.api_url {
+            Some(setting) => {
+                profile.service.api_url = setting.value.clone();
+                setting.origin.clone()
+            }
+            None => Origin::ConfigFile,
+        };
+
+        // An overridden token is used as is, without running the profile's token command.
+        let token_origin = match &overrides.token {
+            Some(setting) => {
+                profile.service.auth_token = setting.value.clone();
+                setting.origin.clone()
+            }
+            None => profile.resolve_token()?,
+        };
+
+        profile.service.client_config = profile
+            .default_behavior
+            .client_config(overrides.timeout, overrides.retries);
+
+        Ok(EffectiveProfile {
+            profile,
+            profile_origin,
+            api_url_origin,
+            token_origin,
+        })
+    }
+
+    /// Parse a configuration file, upgrading older layouts to the current one. Warns about
+    /// settings mp does not know, which are dropped when the file is saved.
+    pub fn parse(config_content: &str) -> Result<Self, ConfigError> {
+        let mut table: Table = toml::from_str(config_content)?;
+        let loaded_version = migrate(&mut table)?;
+
+        for key in unknown_keys(&table) {
+            eprintln!(
+                "Warning: unknown setting '{}' in the configuration file will be removed the \
+                 next time mp saves the file.",
+                key
+            );
+        }
+
+        let mut app_config: AppConfig = Value::Table(table).try_into()?;
+        app_config.loaded_version = Some(loaded_version);
+        Ok(app_config)
+    }
+
+    /// Get the version of the configuration file as it was loaded, if it was loaded from a file.
+    pub fn loaded_version(&self) -> Option<u32> {
+        self.loaded_version
+    }
+
+    /// Serialize the configuration in the current layout.
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Get the named profile as stored in the configuration file.
+    fn stored_profile(&self, name: &str) -> io::Result<Profile> {
+        self.profiles.get(name).cloned().ok_or_else(|| {
             io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Failed to parse configuration file: {}", e),
+                io::ErrorKind::NotFound,
+                format!(
+                    "Profile '{}' not found in the configuration file. Available profiles: {}. \
+                     Run 'mp configure --profile {}' to create it.",
+                    name,
+                    self.profile_names().join(", "),
+                    name
+                ),
             )
-        })?;
+        })
+    }
 
-        Ok(app_config)
+    /// Add a profile, replacing any existing profile with the same name.
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profiles.insert(profile.name.clone(), profile);
     }
 
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// Check if any profile stores its token in the configuration file.
+    fn has_inline_tokens(&self) -> bool {
+        self.profiles.values().any(Profile::has_inline_token)
+    }
+
+    /// Save the configuration file. If it holds any tokens, only the current user may read it.
+    ///
+    /// A configuration file loaded in an older layout is backed up before it is overwritten.
     pub fn save(&self) -> io::Result<String> {
         let config_path = AppConfig::get_config_file_path()?;
         fs::create_dir_all(Path::new(&config_path).parent().unwrap())?;
 
-        let config_content = toml::to_string(self).map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Failed to serialize config: {}", e),
-            )
-        })?;
+        let config_content = self
+            .to_toml()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
 
-        fs::write(&config_path, config_content)?;
+        if let Some(loaded_version) = self.loaded_version
+            && loaded_version < CURRENT_CONFIG_VERSION
+            && Path::new(&config_path).exists()
+        {
+            // The previous file may hold tokens even if the new one does not, so the backup is
+            // always restricted to the current user.
+            let backup_path = format!("{}.v{}.bak", config_path, loaded_version);
+            let old_content = fs::read_to_string(&config_path)?;
+            write_config_file(Path::new(&backup_path), &old_content, true)?;
+            eprintln!(
+                "Upgraded the configuration file to version {}. The previous file was backed up \
+                 to '{}'.",
+                CURRENT_CONFIG_VERSION, backup_path
+            );
+        }
+
+        write_config_file(
+            Path::new(&config_path),
+            &config_content,
+            self.has_inline_tokens(),
+        )?;
         Ok(config_path)
     }
 }
+
+/// Write the configuration file, restricting it to the current user if it holds secrets.
+#[cfg(unix)]
+pub(crate) fn write_config_file(path: &Path, content: &str, holds_secrets: bool) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if holds_secrets {
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode only applies to new files.
+    if holds_secrets {
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content.as_bytes())
+}
+
+/// Write the configuration file, restricting it to the current user if it holds secrets.
+#[cfg(not(unix))]
+pub(crate) fn write_config_file(
+    path: &Path,
+    content: &str,
+    _holds_secrets: bool,
+) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+/// Warn if users other than the owner can read the configuration file.
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = fs::metadata(path)
+        && metadata.permissions().mode() & 0o077 != 0
+    {
+        eprintln!(
+            "Warning: the configuration file '{}' contains authentication tokens and can be \
+             read by other users. Run 'chmod 600 {}' to fix this.",
+            path.display(),
+            path.display()
+        );
+    }
+}
+
+/// Warn if users other than the owner can read the configuration file.
+#[cfg(not(unix))]
+fn warn_if_readable_by_others(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_service_config_as_default_profile() {
+        let config_content = r#"
+            [service]
+            api_url = "https://micro.blog/micropub"
+            auth_token = "secret"
+
+            [default_behavior]
+            quiet = false
+            extract_title = true
+        "#;
+        let app_config = AppConfig::parse(config_content).unwrap();
+
+        assert_eq!(app_config.default_profile, DEFAULT_PROFILE_NAME);
+        let profile = app_config.stored_profile(DEFAULT_PROFILE_NAME).unwrap();
+        assert_eq!(profile.service.api_url, "https://micro.blog/micropub");
+        assert!(profile.default_behavior.extract_title);
+    }
+
+    #[test]
+    fn test_parse_profiles_config() {
+        let config_content = r#"
+            default_profile = "team"
+
+            [profiles.personal.service]
+            api_url = "https://personal.example/micropub"
+            auth_token = "one"
+
+            [profiles.personal.default_behavior]
+            quiet = false
+            extract_title = false
+
+            [profiles.team.service]
+            api_url = "https://team.example/micropub"
+            auth_token = "two"
+
+            [profiles.team.default_behavior]
+            quiet = true
+            extract_title = false
+        "#;
+        let app_config = AppConfig::parse(config_content).unwrap();
+
+        assert_eq!(app_config.default_profile, "team");
+        assert_eq!(
+            app_config.stored_profile("team").unwrap().service.api_url,
+            "https://team.example/micropub"
+        );
+        assert_eq!(
+            app_config
+                .stored_profile("personal")
+                .unwrap()
+                .service
+                .auth_token,
+            "one"
+        );
+        assert!(app_config.stored_profile("missing").is_err());
+    }
+
+    #[test]
+    fn test_token_source_round_trip() {
+        let config_content = r#"
+            default_profile = "personal"
+
+            [profiles.personal]
+            token_command = "pass show micropub"
+
+            [profiles.personal.service]
+            api_url = "https://personal.example/micropub"
+
+            [profiles.personal.default_behavior]
+            quiet = false
+            extract_title = false
+        "#;
+        let app_config = AppConfig::parse(config_content).unwrap();
+        assert!(!app_config.has_inline_tokens());
+
+        let saved = toml::to_string(&app_config).unwrap();
+        assert!(saved.contains(r#"token_command = "pass show micropub""#));
+        assert!(!saved.contains("auth_token"));
+    }
+
+    #[test]
+    fn test_profile_without_any_token_is_rejected() {
+        let config_content = r#"
+            default_profile = "personal"
+
+            [profiles.personal.service]
+            api_url = "https://personal.example/micropub"
+
+            [profiles.personal.default_behavior]
+            quiet = false
+            extract_title = false
+        "#;
+        let app_config = AppConfig::parse(config_content).unwrap();
+        let mut profile = app_config.stored_profile("personal").unwrap();
+        assert!(profile.resolve_token().is_err());
+    }
+}
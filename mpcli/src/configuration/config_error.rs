@@ -0,0 +1,36 @@
+use std::io;
+use thiserror::Error;
+
+/// Represents an error reading or changing the configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unknown configuration key '{key}'. Valid keys are: {valid_keys}")]
+    UnknownKey { key: String, valid_keys: String },
+    #[error("Invalid value '{value}' for '{key}': expected {expected}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("'{0}' is required and cannot be unset")]
+    RequiredKey(String),
+    #[error("No {0} was given. Pass it as a flag or omit --non-interactive to be prompted for it")]
+    MissingValue(String),
+    #[error("Profile '{0}' not found in the configuration file")]
+    ProfileNotFound(String),
+    #[error(
+        "Profile '{name}' not found in the configuration file. Available profiles are: {available}"
+    )]
+    UnknownProfile { name: String, available: String },
+    #[error(
+        "The configuration file has version {0}, which is newer than this version of mp supports. \
+         Please upgrade mp."
+    )]
+    UnsupportedVersion(u32),
+    #[error("Failed to parse configuration file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Failed to serialize configuration file: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
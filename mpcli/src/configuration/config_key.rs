@@ -0,0 +1,430 @@
+use mplib::RequestFormat;
+use std::str::FromStr;
+use toml::{Table, Value};
+
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::config_error::ConfigError;
+use crate::configuration::default_behavior::DefaultBehavior;
+use crate::configuration::profile::Profile;
+
+/// Represents a setting that can be read and changed with `mp config get/set/unset`.
+///
+/// Keys are named after their place in the configuration file: `default_profile` is global,
+/// and the other keys belong to the selected profile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigKey {
+    DefaultProfile,
+    ApiUrl,
+    AuthToken,
+    RequestFormat,
+    TokenEndpoint,
+    TokenCommand,
+    TokenEnv,
+    TokenFile,
+    Quiet,
+    ExtractTitle,
+    SyndicateTo,
+    Destination,
+    ComposeInEditor,
+    Timeout,
+    Retries,
+}
+
+const ALL_KEYS: [ConfigKey; 15] = [
+    ConfigKey::DefaultProfile,
+    ConfigKey::ApiUrl,
+    ConfigKey::AuthToken,
+    ConfigKey::RequestFormat,
+    ConfigKey::TokenEndpoint,
+    ConfigKey::TokenCommand,
+    ConfigKey::TokenEnv,
+    ConfigKey::TokenFile,
+    ConfigKey::Quiet,
+    ConfigKey::ExtractTitle,
+    ConfigKey::SyndicateTo,
+    ConfigKey::Destination,
+    ConfigKey::ComposeInEditor,
+    ConfigKey::Timeout,
+    ConfigKey::Retries,
+];
+
+impl ConfigKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigKey::DefaultProfile => "default_profile",
+            ConfigKey::ApiUrl => "service.api_url",
+            ConfigKey::AuthToken => "service.auth_token",
+            ConfigKey::RequestFormat => "service.request_format",
+            ConfigKey::TokenEndpoint => "service.token_endpoint",
+            ConfigKey::TokenCommand => "token_command",
+            ConfigKey::TokenEnv => "token_env",
+            ConfigKey::TokenFile => "token_file",
+            ConfigKey::Quiet => "default_behavior.quiet",
+            ConfigKey::ExtractTitle => "default_behavior.extract_title",
+            ConfigKey::SyndicateTo => "default_behavior.syndicate_to",
+            ConfigKey::Destination => "default_behavior.destination",
+            ConfigKey::ComposeInEditor => "default_behavior.compose_in_editor",
+            ConfigKey::Timeout => "default_behavior.timeout",
+            ConfigKey::Retries => "default_behavior.retries",
+        }
+    }
+
+    /// Get the value of the setting, or None if it is not set. Lists are joined with commas.
+    pub fn get(
+        &self,
+        app_config: &AppConfig,
+        profile_name: &str,
+    ) -> Result<Option<String>, ConfigError> {
+        if *self == ConfigKey::DefaultProfile {
+            return Ok(Some(app_config.default_profile.clone()));
+        }
+
+        let profile = app_config
+            .profiles
+            .get(profile_name)
+            .ok_or_else(|| ConfigError::ProfileNotFound(profile_name.to_string()))?;
+        let service = &profile.service;
+        let behavior = &profile.default_behavior;
+        let non_empty = |value: &String| (!value.is_empty()).then(|| value.clone());
+
+        Ok(match self {
+            ConfigKey::DefaultProfile => unreachable!(),
+            ConfigKey::ApiUrl => non_empty(&service.api_url),
+            ConfigKey::AuthToken => non_empty(&service.auth_token),
+            ConfigKey::RequestFormat => Some(service.request_format.as_str().to_string()),
+            ConfigKey::TokenEndpoint => service.token_endpoint.clone(),
+            ConfigKey::TokenCommand => profile.token_source.token_command.clone(),
+            ConfigKey::TokenEnv => profile.token_source.token_env.clone(),
+            ConfigKey::TokenFile => profile.token_source.token_file.clone(),
+            ConfigKey::Quiet => Some(behavior.quiet.to_string()),
+            ConfigKey::ExtractTitle => Some(behavior.extract_title.to_string()),
+            ConfigKey::SyndicateTo => non_empty(&behavior.syndicate_to.join(",")),
+            ConfigKey::Destination => behavior.destination.clone(),
+            ConfigKey::ComposeInEditor => Some(behavior.compose_in_editor.to_string()),
+            ConfigKey::Timeout => behavior.timeout.map(|timeout| timeout.to_string()),
+            ConfigKey::Retries => behavior.retries.map(|retries| retries.to_string()),
+        })
+    }
+
+    /// Set the setting to a value, creating the profile if it does not exist yet.
+    pub fn set(
+        &self,
+        app_config: &mut AppConfig,
+        profile_name: &str,
+        value: &str,
+    ) -> Result<(), ConfigError> {
+        if *self == ConfigKey::DefaultProfile {
+            let name = self.parse_non_empty(value)?;
+            if !app_config.profiles.contains_key(&name) {
+                return Err(ConfigError::UnknownProfile {
+                    name,
+                    available: app_config
+                        .profiles
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+            }
+            app_config.default_profile = name;
+            return Ok(());
+        }
+
+        let profile = profile_mut(app_config, profile_name);
+        let optional = |value: &str| Some(value.to_string());
+
+        match self {
+            ConfigKey::DefaultProfile => unreachable!(),
+            ConfigKey::ApiUrl => profile.service.api_url = self.parse_non_empty(value)?,
+            ConfigKey::AuthToken => profile.service.auth_token = self.parse_non_empty(value)?,
+            ConfigKey::RequestFormat => {
+                profile.service.request_format = self.parse_request_format(value)?
+            }
+            ConfigKey::TokenEndpoint => profile.service.token_endpoint = optional(value),
+            ConfigKey::TokenCommand => profile.token_source.token_command = optional(value),
+            ConfigKey::TokenEnv => profile.token_source.token_env = optional(value),
+            ConfigKey::TokenFile => profile.token_source.token_file = optional(value),
+            ConfigKey::Quiet => profile.default_behavior.quiet = self.parse_bool(value)?,
+            ConfigKey::ExtractTitle => {
+                profile.default_behavior.extract_title = self.parse_bool(value)?
+            }
+            ConfigKey::SyndicateTo => {
+                profile.default_behavior.syndicate_to =
+                    DefaultBehavior::parse_syndication_targets(value)
+            }
+            ConfigKey::Destination => profile.default_behavior.destination = optional(value),
+            ConfigKey::ComposeInEditor => {
+                profile.default_behavior.compose_in_editor = self.parse_bool(value)?
+            }
+            ConfigKey::Timeout => {
+                profile.default_behavior.timeout =
+                    Some(self.parse_number(value, 1, "a number of seconds greater than 0")?)
+            }
+            ConfigKey::Retries => {
+                profile.default_behavior.retries =
+                    Some(self.parse_number(value, 0, "a number of retries")?)
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the setting, or reset it to its default value.
+    pub fn unset(&self, app_config: &mut AppConfig, profile_name: &str) -> Result<(), ConfigError> {
+        if matches!(self, ConfigKey::DefaultProfile | ConfigKey::ApiUrl) {
+            return Err(ConfigError::RequiredKey(self.as_str().to_string()));
+        }
+
+        let profile = app_config
+            .profiles
+            .get_mut(profile_name)
+            .ok_or_else(|| ConfigError::ProfileNotFound(profile_name.to_string()))?;
+
+        match self {
+            ConfigKey::DefaultProfile | ConfigKey::ApiUrl => unreachable!(),
+            ConfigKey::AuthToken => profile.service.auth_token.clear(),
+            ConfigKey::RequestFormat => profile.service.request_format = RequestFormat::default(),
+            ConfigKey::TokenEndpoint => profile.service.token_endpoint = None,
+            ConfigKey::TokenCommand => profile.token_source.token_command = None,
+            ConfigKey::TokenEnv => profile.token_source.token_env = None,
+            ConfigKey::TokenFile => profile.token_source.token_file = None,
+            ConfigKey::Quiet => profile.default_behavior.quiet = false,
+            ConfigKey::ExtractTitle => profile.default_behavior.extract_title = false,
+            ConfigKey::SyndicateTo => profile.default_behavior.syndicate_to.clear(),
+            ConfigKey::Destination => profile.default_behavior.destination = None,
+            ConfigKey::ComposeInEditor => profile.default_behavior.compose_in_editor = false,
+            ConfigKey::Timeout => profile.default_behavior.timeout = None,
+            ConfigKey::Retries => profile.default_behavior.retries = None,
+        }
+        Ok(())
+    }
+
+    fn invalid_value(&self, value: &str, expected: &'static str) -> ConfigError {
+        ConfigError::InvalidValue {
+            key: self.as_str().to_string(),
+            value: value.to_string(),
+            expected,
+        }
+    }
+
+    fn parse_non_empty(&self, value: &str) -> Result<String, ConfigError> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(self.invalid_value(value, "a non-empty value"));
+        }
+        Ok(value.to_string())
+    }
+
+    fn parse_bool(&self, value: &str) -> Result<bool, ConfigError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" | "true" | "on" | "1" => Ok(true),
+            "n" | "no" | "false" | "off" | "0" => Ok(false),
+            _ => Err(self.invalid_value(value, "y or n")),
+        }
+    }
+
+    fn parse_number<T: FromStr + PartialOrd>(
+        &self,
+        value: &str,
+        minimum: T,
+        expected: &'static str,
+    ) -> Result<T, ConfigError> {
+        match value.trim().parse() {
+            Ok(number) if number >= minimum => Ok(number),
+            _ => Err(self.invalid_value(value, expected)),
+        }
+    }
+
+    fn parse_request_format(&self, value: &str) -> Result<RequestFormat, ConfigError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "form" => Ok(RequestFormat::Form),
+            "json" => Ok(RequestFormat::Json),
+            _ => Err(self.invalid_value(value, "form or json")),
+        }
+    }
+}
+
+impl FromStr for ConfigKey {
+    type Err = ConfigError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        ALL_KEYS
+            .iter()
+            .find(|candidate| candidate.as_str() == key)
+            .copied()
+            .ok_or_else(|| ConfigError::UnknownKey {
+                key: key.to_string(),
+                valid_keys: ALL_KEYS.map(|key| key.as_str()).join(", "),
+            })
+    }
+}
+
+/// Find the settings in a parsed configuration file that are not known to mp, as dotted keys.
+pub fn unknown_keys(table: &Table) -> Vec<String> {
+    let is_profile_key = |key: &str| {
+        ALL_KEYS
+            .iter()
+            .any(|known| *known != ConfigKey::DefaultProfile && known.as_str() == key)
+    };
+
+    let mut unknown = Vec::new();
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("config_version" | "default_profile", _) => {}
+            ("profiles", Value::Table(profiles)) => {
+                for (name, profile) in profiles {
+                    let Value::Table(profile) = profile else {
+                        continue;
+                    };
+                    for (key, value) in profile {
+                        let keys: Vec<String> = match value {
+                            Value::Table(section) => section
+                                .keys()
+                                .map(|section_key| format!("{}.{}", key, section_key))
+                                .collect(),
+                            _ => vec![key.clone()],
+                        };
+                        unknown.extend(
+                            keys.into_iter()
+                                .filter(|key| !is_profile_key(key))
+                                .map(|key| format!("profiles.{}.{}", name, key)),
+                        );
+                    }
+                }
+            }
+            _ => unknown.push(key.clone()),
+        }
+    }
+    unknown
+}
+
+/// Get the named profile, creating an empty one if it does not exist yet.
+fn profile_mut<'a>(app_config: &'a mut AppConfig, profile_name: &str) -> &'a mut Profile {
+    if !app_config.profiles.contains_key(profile_name) {
+        if app_config.profiles.is_empty() {
+            app_config.default_profile = profile_name.to_string();
+        }
+        app_config.set_profile(Profile::new(
+            profile_name.to_string(),
+            mplib::MicropubService::new(String::new(), String::new()),
+            DefaultBehavior::new(false, false, Vec::new()),
+        ));
+    }
+    app_config.profiles.get_mut(profile_name).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            "service.api_url".parse::<ConfigKey>().unwrap(),
+            ConfigKey::ApiUrl
+        );
+        assert!(matches!(
+            "service.apiurl".parse::<ConfigKey>(),
+            Err(ConfigError::UnknownKey { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_get_and_unset() {
+        let mut app_config = AppConfig::new();
+        ConfigKey::ApiUrl
+            .set(&mut app_config, "ci", "https://example.com/micropub")
+            .unwrap();
+        ConfigKey::Quiet.set(&mut app_config, "ci", "yes").unwrap();
+        ConfigKey::SyndicateTo
+            .set(&mut app_config, "ci", "Mastodon, Bluesky")
+            .unwrap();
+
+        assert_eq!(app_config.default_profile, "ci");
+        assert_eq!(
+            ConfigKey::Quiet.get(&app_config, "ci").unwrap().as_deref(),
+            Some("true")
+        );
+        assert_eq!(
+            ConfigKey::SyndicateTo
+                .get(&app_config, "ci")
+                .unwrap()
+                .as_deref(),
+            Some("Mastodon,Bluesky")
+        );
+
+        ConfigKey::SyndicateTo.unset(&mut app_config, "ci").unwrap();
+        assert_eq!(ConfigKey::SyndicateTo.get(&app_config, "ci").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let table: Table = toml::from_str(
+            r#"
+            default_profile = "blog"
+            theme = "dark"
+
+            [profiles.blog]
+            token_env = "MP_TOKEN"
+
+            [profiles.blog.service]
+            api_url = "https://example.com/micropub"
+            timeout = 30
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            unknown_keys(&table),
+            vec!["profiles.blog.service.timeout", "theme"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let mut app_config = AppConfig::new();
+        assert!(matches!(
+            ConfigKey::Quiet.set(&mut app_config, "ci", "maybe"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            ConfigKey::RequestFormat.set(&mut app_config, "ci", "xml"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            ConfigKey::Timeout.set(&mut app_config, "ci", "0"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            ConfigKey::Retries.set(&mut app_config, "ci", "-1"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            ConfigKey::ApiUrl.unset(&mut app_config, "ci"),
+            Err(ConfigError::RequiredKey(_))
+        ));
+        assert!(matches!(
+            ConfigKey::Quiet.get(&app_config, "missing"),
+            Err(ConfigError::ProfileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_default_profile_must_exist() {
+        let mut app_config = AppConfig::new();
+        ConfigKey::ApiUrl
+            .set(&mut app_config, "blog", "https://example.com/micropub")
+            .unwrap();
+        ConfigKey::ApiUrl
+            .set(&mut app_config, "notes", "https://example.com/micropub")
+            .unwrap();
+
+        assert!(matches!(
+            ConfigKey::DefaultProfile.set(&mut app_config, "blog", "missing"),
+            Err(ConfigError::UnknownProfile { name, available })
+                if name == "missing" && available == "blog, notes"
+        ));
+        ConfigKey::DefaultProfile
+            .set(&mut app_config, "blog", "notes")
+            .unwrap();
+        assert_eq!(app_config.default_profile, "notes");
+    }
+}
@@ -1,24 +1,63 @@
+use mplib::ClientConfig;
 use serde::{Deserialize, Serialize};
-use std::io;
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DefaultBehavior {
     pub quiet: bool,
     pub extract_title: bool,
+    /// Syndication targets (UIDs or names) used when none are given on the command line.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub syndicate_to: Vec<String>,
+    /// Destination (UID or name) used when none is given on the command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    /// Open the text editor to compose posts when no content is given on the command line.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compose_in_editor: bool,
+    /// How long to wait for a request to complete, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// How many times a failed request is retried, when it is safe to send it again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
 }
 
 impl DefaultBehavior {
-    pub fn new(quiet: bool, extract_title: bool) -> Self {
+    pub fn new(quiet: bool, extract_title: bool, syndicate_to: Vec<String>) -> Self {
         DefaultBehavior {
             quiet,
             extract_title,
+            syndicate_to,
+            destination: None,
+            compose_in_editor: false,
+            timeout: None,
+            retries: None,
         }
     }
 
-    pub fn from_args(quiet: String, extract_title: String) -> io::Result<Self> {
-        let is_quiet = quiet.trim().eq_ignore_ascii_case("y");
-        let should_extract_title = extract_title.trim().eq_ignore_ascii_case("y");
+    /// Parse a comma-separated list of syndication targets.
+    pub fn parse_syndication_targets(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|target| !target.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
 
-        Ok(DefaultBehavior::new(is_quiet, should_extract_title))
+    /// Get the HTTP client settings of the profile. A timeout or number of retries given on the
+    /// command line takes precedence over the profile's.
+    pub fn client_config(&self, timeout: Option<u64>, retries: Option<u32>) -> ClientConfig {
+        let mut client_config = ClientConfig {
+            user_agent: concat!("mp/", env!("CARGO_PKG_VERSION")).to_string(),
+            ..ClientConfig::default()
+        };
+        if let Some(timeout) = timeout.or(self.timeout) {
+            client_config.request_timeout = Duration::from_secs(timeout);
+        }
+        if let Some(retries) = retries.or(self.retries) {
+            client_config.max_retries = retries;
+        }
+        client_config
     }
 }
@@ -0,0 +1,114 @@
+use toml::{Table, Value};
+
+use crate::configuration::app_config::DEFAULT_PROFILE_NAME;
+use crate::configuration::config_error::ConfigError;
+
+/// The version of the configuration file layout written by this version of mp.
+pub const CURRENT_CONFIG_VERSION: u32 = 2;
+
+/// Upgrades a configuration file from the version it is indexed by to the next one.
+type Migration = fn(&mut Table);
+
+/// The migrations, in order. The migration at index N upgrades version N + 1 to version N + 2.
+const MIGRATIONS: [Migration; (CURRENT_CONFIG_VERSION - 1) as usize] = [migrate_v1_to_v2];
+
+/// Upgrade a parsed configuration file to the current version. Returns the version the file
+/// had before the upgrade.
+pub fn migrate(table: &mut Table) -> Result<u32, ConfigError> {
+    let from_version = config_version(table)?;
+    if from_version > CURRENT_CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion(from_version));
+    }
+
+    for migration in &MIGRATIONS[(from_version - 1) as usize..] {
+        migration(table);
+    }
+    table.insert(
+        "config_version".to_string(),
+        Value::Integer(CURRENT_CONFIG_VERSION.into()),
+    );
+
+    Ok(from_version)
+}
+
+/// Get the version of a configuration file. Files written before the version was recorded are
+/// recognized by their layout.
+fn config_version(table: &Table) -> Result<u32, ConfigError> {
+    match table.get("config_version") {
+        Some(Value::Integer(version)) if *version >= 1 => {
+            u32::try_from(*version).map_err(|_| ConfigError::UnsupportedVersion(u32::MAX))
+        }
+        Some(value) => Err(ConfigError::InvalidValue {
+            key: "config_version".to_string(),
+            value: value.to_string(),
+            expected: "a positive integer",
+        }),
+        None if table.contains_key("service") => Ok(1),
+        None => Ok(2),
+    }
+}
+
+/// Version 1 holds a single service in top-level `[service]` and `[default_behavior]` tables.
+/// Version 2 holds named profiles, and the single service becomes the default profile.
+fn migrate_v1_to_v2(table: &mut Table) {
+    let mut profile = Table::new();
+    for key in ["service", "default_behavior"] {
+        if let Some(value) = table.remove(key) {
+            profile.insert(key.to_string(), value);
+        }
+    }
+
+    let mut profiles = Table::new();
+    profiles.insert(DEFAULT_PROFILE_NAME.to_string(), Value::Table(profile));
+    table.insert("profiles".to_string(), Value::Table(profiles));
+    table.insert(
+        "default_profile".to_string(),
+        Value::String(DEFAULT_PROFILE_NAME.to_string()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v1() {
+        let mut table: Table = toml::from_str(
+            r#"
+            [service]
+            api_url = "https://micro.blog/micropub"
+            auth_token = "secret"
+
+            [default_behavior]
+            quiet = false
+            extract_title = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&mut table).unwrap(), 1);
+        assert_eq!(table["config_version"].as_integer(), Some(2));
+        assert_eq!(table["default_profile"].as_str(), Some("default"));
+        assert_eq!(
+            table["profiles"]["default"]["service"]["api_url"].as_str(),
+            Some("https://micro.blog/micropub")
+        );
+        assert!(!table.contains_key("service"));
+    }
+
+    #[test]
+    fn test_migrate_unversioned_profiles() {
+        let mut table: Table = toml::from_str(r#"default_profile = "blog""#).unwrap();
+        assert_eq!(migrate(&mut table).unwrap(), 2);
+        assert_eq!(table["default_profile"].as_str(), Some("blog"));
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let mut table: Table = toml::from_str("config_version = 99").unwrap();
+        assert!(matches!(
+            migrate(&mut table),
+            Err(ConfigError::UnsupportedVersion(99))
+        ));
+    }
+}
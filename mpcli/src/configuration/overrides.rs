@@ -0,0 +1,65 @@
+use std::fmt;
+
+use crate::configuration::profile::Profile;
+
+/// Where the effective value of a setting comes from. Command-line flags take precedence over
+/// environment variables, which take precedence over the configuration file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// A command-line flag, such as `--api-url`.
+    Flag(String),
+    /// An environment variable, such as `MP_API_URL`.
+    Environment(String),
+    /// The configuration file.
+    ConfigFile,
+    /// The token source of the profile, such as `token_command`.
+    TokenSource(String),
+    /// The built-in default.
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Flag(flag) => write!(f, "command-line flag {}", flag),
+            Origin::Environment(variable) => write!(f, "environment variable {}", variable),
+            Origin::ConfigFile => write!(f, "configuration file"),
+            Origin::TokenSource(key) => write!(f, "{} in the configuration file", key),
+            Origin::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A value given on the command line or in the environment, with its origin.
+#[derive(Clone, Debug)]
+pub struct Setting {
+    pub value: String,
+    pub origin: Origin,
+}
+
+/// Settings that override or replace the configuration file.
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    pub profile: Option<Setting>,
+    pub api_url: Option<Setting>,
+    pub token: Option<Setting>,
+    /// Request timeout in seconds, from `--timeout` or `MP_TIMEOUT`.
+    pub timeout: Option<u64>,
+    /// Number of retries of failed requests, from `--retries` or `MP_RETRIES`.
+    pub retries: Option<u32>,
+}
+
+impl Overrides {
+    /// Get the name of the profile to use, if one was given.
+    pub fn profile_name(&self) -> Option<&String> {
+        self.profile.as_ref().map(|setting| &setting.value)
+    }
+}
+
+/// A profile with its overrides applied, together with the origin of each of its settings.
+pub struct EffectiveProfile {
+    pub profile: Profile,
+    pub profile_origin: Origin,
+    pub api_url_origin: Origin,
+    pub token_origin: Origin,
+}
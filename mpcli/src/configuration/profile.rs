@@ -0,0 +1,60 @@
+use mplib::MicropubService;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use crate::configuration::default_behavior::DefaultBehavior;
+use crate::configuration::overrides::Origin;
+use crate::configuration::token_source::TokenSource;
+
+/// A named Micropub service together with the default behavior used when publishing to it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// The name of the profile, as used with `--profile`. Set when the profile is loaded.
+    #[serde(skip)]
+    pub name: String,
+    /// Where to read the token from. The token stored in `service` is only a fallback.
+    #[serde(flatten)]
+    pub token_source: TokenSource,
+    pub service: MicropubService,
+    pub default_behavior: DefaultBehavior,
+}
+
+impl Profile {
+    pub fn new(name: String, service: MicropubService, default_behavior: DefaultBehavior) -> Self {
+        Profile {
+            name,
+            token_source: TokenSource::default(),
+            service,
+            default_behavior,
+        }
+    }
+
+    /// Check if a token is stored in the configuration file, even if only as a fallback.
+    pub fn has_inline_token(&self) -> bool {
+        !self.service.auth_token.is_empty()
+    }
+
+    /// Read the token from the token source, if any, falling back to the token stored in the
+    /// configuration file. Returns where the token was read from.
+    pub fn resolve_token(&mut self) -> io::Result<Origin> {
+        match self.token_source.read_token() {
+            Ok(Some(token)) => {
+                self.service.auth_token = token;
+                return Ok(Origin::TokenSource(self.token_source.key().to_string()));
+            }
+            Ok(None) if self.service.auth_token.is_empty() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Profile '{}' has no authentication token", self.name),
+                ));
+            }
+            Ok(None) => {}
+            Err(e) if self.service.auth_token.is_empty() => return Err(e),
+            Err(e) => eprintln!(
+                "Warning: {}. Using the token stored in the configuration file.",
+                e
+            ),
+        }
+        Ok(Origin::ConfigFile)
+    }
+}
@@ -0,0 +1,94 @@
+use mplib::{ServerConfig, query_config};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::configuration::app_config::AppConfig;
+use crate::configuration::profile::Profile;
+
+/// A server configuration cached for the Micropub endpoint it was queried from.
+#[derive(Serialize, Deserialize)]
+pub struct ServerConfigCache {
+    pub api_url: String,
+    pub server_config: ServerConfig,
+}
+
+impl ServerConfigCache {
+    /// Get the path of the cache file, which is stored next to the configuration file.
+    pub fn get_cache_file_path() -> io::Result<String> {
+        let config_path = AppConfig::get_config_file_path()?;
+        let cache_path = Path::new(&config_path).with_file_name("server_config.json");
+
+        Ok(cache_path.to_str().unwrap().to_string())
+    }
+
+    /// Load the cached server configurations of all profiles, keyed by profile name.
+    /// A cache file that cannot be parsed is treated as empty.
+    fn load_all() -> io::Result<BTreeMap<String, ServerConfigCache>> {
+        let cache_path = ServerConfigCache::get_cache_file_path()?;
+        if !Path::new(&cache_path).exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let cache_content = fs::read_to_string(cache_path)?;
+        Ok(serde_json::from_str(&cache_content).unwrap_or_default())
+    }
+
+    /// Load the cached server configuration for the given profile, if one exists.
+    pub fn load(profile: &Profile) -> io::Result<Option<ServerConfig>> {
+        let mut caches = ServerConfigCache::load_all()?;
+
+        Ok(caches
+            .remove(&profile.name)
+            // A cache for a different endpoint is stale.
+            .filter(|cache| cache.api_url == profile.service.api_url)
+            .map(|cache| cache.server_config))
+    }
+
+    /// Cache the server configuration for the given profile.
+    pub fn save(profile: &Profile, server_config: &ServerConfig) -> io::Result<String> {
+        let mut caches = ServerConfigCache::load_all()?;
+        caches.insert(
+            profile.name.clone(),
+            ServerConfigCache {
+                api_url: profile.service.api_url.clone(),
+                server_config: server_config.clone(),
+            },
+        );
+
+        let cache_path = ServerConfigCache::get_cache_file_path()?;
+        fs::create_dir_all(Path::new(&cache_path).parent().unwrap())?;
+
+        let cache_content = serde_json::to_string_pretty(&caches).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to serialize server configuration cache: {}", e),
+            )
+        })?;
+
+        fs::write(&cache_path, cache_content)?;
+        Ok(cache_path)
+    }
+
+    /// Query the server configuration of the given profile's service and cache it.
+    pub async fn refresh(profile: &Profile) -> io::Result<ServerConfig> {
+        let server_config = query_config(&profile.service)
+            .await
+            .map_err(io::Error::other)?;
+
+        ServerConfigCache::save(profile, &server_config)?;
+
+        Ok(server_config)
+    }
+
+    /// Get the server configuration of the given profile from the cache, querying the server
+    /// if nothing is cached yet.
+    pub async fn load_or_refresh(profile: &Profile) -> io::Result<ServerConfig> {
+        match ServerConfigCache::load(profile) {
+            Ok(Some(server_config)) => Ok(server_config),
+            _ => ServerConfigCache::refresh(profile).await,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::{env, fs};
+
+use crate::configuration::app_config::write_config_file;
+
+/// Where to read a profile's authentication token from instead of storing it in the
+/// configuration file. At most one source is used, in the order command, environment variable,
+/// file.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TokenSource {
+    /// A shell command that prints the token, such as `pass show micropub`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+    /// The name of an environment variable holding the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    /// The path of a file holding the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<String>,
+}
+
+impl TokenSource {
+    /// Check if a source is configured.
+    pub fn is_configured(&self) -> bool {
+        self.token_command.is_some() || self.token_env.is_some() || self.token_file.is_some()
+    }
+
+    /// Get the configuration key of the source in use.
+    pub fn key(&self) -> &'static str {
+        if self.token_command.is_some() {
+            "token_command"
+        } else if self.token_env.is_some() {
+            "token_env"
+        } else {
+            "token_file"
+        }
+    }
+
+    /// Read the token from the configured source. Returns None if no source is configured.
+    pub fn read_token(&self) -> io::Result<Option<String>> {
+        let token = if let Some(command) = &self.token_command {
+            run_token_command(command)?
+        } else if let Some(variable) = &self.token_env {
+            env::var(variable).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Environment variable '{}' is not set", variable),
+                )
+            })?
+        } else if let Some(path) = &self.token_file {
+            fs::read_to_string(path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not read token file '{}': {}", path, e),
+                )
+            })?
+        } else {
+            return Ok(None);
+        };
+
+        let token = token.trim().to_string();
+        if token.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The configured token source returned an empty token",
+            ));
+        }
+        Ok(Some(token))
+    }
+
+    /// Store a token in the source in use, if mp can write to it: only token files can be
+    /// written, and they are restricted to the current user. Returns whether the token was
+    /// stored.
+    pub fn write_token(&self, token: &str) -> io::Result<bool> {
+        let (Some(path), "token_file") = (&self.token_file, self.key()) else {
+            return Ok(false);
+        };
+        write_config_file(Path::new(path), &format!("{}\n", token), true).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not write token file '{}': {}", path, e),
+            )
+        })?;
+        Ok(true)
+    }
+}
+
+/// Run a command through the shell and return its standard output.
+fn run_token_command(command: &str) -> io::Result<String> {
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    let output = shell
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "Token command '{}' failed with {}",
+            command, output.status
+        )));
+    }
+
+    String::from_utf8(output.stdout).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Token command '{}' did not print valid UTF-8", command),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_token_without_source() {
+        assert!(TokenSource::default().read_token().unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_token_from_command() {
+        let source = TokenSource {
+            token_command: Some("echo '  secret  '".to_string()),
+            ..TokenSource::default()
+        };
+        assert_eq!(source.read_token().unwrap().as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_write_token_only_to_token_file() {
+        let path = env::temp_dir().join(format!("mp-token-{}", std::process::id()));
+        let source = TokenSource {
+            token_file: Some(path.to_str().unwrap().to_string()),
+            ..TokenSource::default()
+        };
+        assert!(source.write_token("secret").unwrap());
+        assert_eq!(source.read_token().unwrap().as_deref(), Some("secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(&path).unwrap();
+
+        let source = TokenSource {
+            token_env: Some("MP_TOKEN".to_string()),
+            ..TokenSource::default()
+        };
+        assert!(!source.write_token("secret").unwrap());
+    }
+
+    #[test]
+    fn test_read_token_from_missing_env_var() {
+        let source = TokenSource {
+            token_env: Some("MP_TEST_TOKEN_THAT_IS_NOT_SET".to_string()),
+            ..TokenSource::default()
+        };
+        assert!(source.read_token().is_err());
+    }
+}
@@ -1,5 +1,6 @@
 mod cli;
 mod configuration;
+mod outbox;
 
 use cli::run;
 
@@ -0,0 +1,317 @@
+//! Store posts that could not be published, or that were queued with `--queue`, until they are
+//! published with `mp queue flush`.
+//!
+//! Each queued post is a JSON file named after its ID in the `outbox` folder of the data
+//! directory.
+
+use directories::ProjectDirs;
+use mplib::Post;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::configuration::profile::Profile;
+
+/// Represents the publishing state of a queued post.
+///
+/// A post is marked as being sent before its request is sent, so that a post whose request was
+/// interrupted, or whose response was lost, is never mistaken for one that was not sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueState {
+    /// The post has not been sent, or the server could not be reached or rejected it.
+    Pending,
+    /// The post is being sent. A post left in this state was interrupted while being sent.
+    Sending,
+    /// The request was sent, but its response was lost, so the post may have been published.
+    Uncertain,
+}
+
+impl QueueState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            QueueState::Pending => "pending",
+            QueueState::Sending => "sending",
+            QueueState::Uncertain => "uncertain",
+        }
+    }
+
+    /// Check if the post may already have been published, in which case sending it again could
+    /// publish it twice.
+    pub fn may_be_published(&self) -> bool {
+        *self != QueueState::Pending
+    }
+}
+
+/// A post waiting in the outbox, together with the profile it is published with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedPost {
+    /// The ID of the post in the outbox. Set when the post is added to or loaded from the outbox.
+    #[serde(skip)]
+    pub id: u32,
+    pub profile: String,
+    /// The Micropub endpoint of the profile when the post was queued, so that a post is not
+    /// published to another site if the profile changes in the meantime.
+    pub api_url: String,
+    /// The key that identifies every attempt to publish the post, so that servers supporting
+    /// idempotency keys do not publish it twice.
+    pub idempotency_key: String,
+    /// When the post was queued, in seconds since the Unix epoch.
+    pub queued_at: u64,
+    pub state: QueueState,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub post: Post,
+}
+
+impl QueuedPost {
+    /// Create a queued post for the given profile. The idempotency key must be the one used for
+    /// any attempt to publish the post before it was queued.
+    pub fn new(post: Post, profile: &Profile, idempotency_key: String, state: QueueState) -> Self {
+        QueuedPost {
+            id: 0,
+            profile: profile.name.clone(),
+            api_url: profile.service.api_url.clone(),
+            idempotency_key,
+            queued_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            state,
+            attempts: 0,
+            last_error: None,
+            post,
+        }
+    }
+
+    /// A short description of the post: its title, or the start of its body.
+    pub fn summary(&self) -> String {
+        const MAX_CHARS: usize = 50;
+
+        let text = match &self.post.title {
+            Some(title) => title.as_str(),
+            None => self
+                .post
+                .body
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or(""),
+        };
+        if text.chars().count() > MAX_CHARS {
+            let truncated: String = text.chars().take(MAX_CHARS - 1).collect();
+            format!("{}…", truncated.trim_end())
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Create a key that identifies a post across attempts to publish it.
+pub fn new_idempotency_key() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    format!("mp-{:x}-{:x}", nanos, std::process::id())
+}
+
+/// The folder holding the queued posts.
+pub struct Outbox {
+    dir: PathBuf,
+}
+
+impl Outbox {
+    /// Open the outbox in the data directory, creating it if needed.
+    pub fn open() -> io::Result<Self> {
+        let proj_dirs = ProjectDirs::from("", "", "mp").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Could not determine project directories",
+            )
+        })?;
+        Outbox::at(proj_dirs.data_dir().join("outbox"))
+    }
+
+    fn at(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Outbox { dir })
+    }
+
+    /// List the queued posts, oldest first.
+    pub fn entries(&self) -> io::Result<Vec<QueuedPost>> {
+        let mut ids: Vec<u32> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let file_name = entry.ok()?.file_name();
+                file_name.to_str()?.strip_suffix(".json")?.parse().ok()
+            })
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter().map(|id| self.get(id)).collect()
+    }
+
+    /// Load the queued post with the given ID.
+    pub fn get(&self, id: u32) -> io::Result<QueuedPost> {
+        let content = fs::read_to_string(self.entry_path(id)).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(e.kind(), format!("There is no queued post with ID {}", id))
+            } else {
+                e
+            }
+        })?;
+        let mut entry: QueuedPost = serde_json::from_str(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Queued post {} could not be read: {}", id, e),
+            )
+        })?;
+        entry.id = id;
+        Ok(entry)
+    }
+
+    /// Add a post to the outbox, and return its ID.
+    pub fn add(&self, entry: QueuedPost) -> io::Result<u32> {
+        let content = serde_json::to_string_pretty(&entry)?;
+        let mut id = self.entries()?.last().map_or(1, |last| last.id + 1);
+        // Another process may add a post at the same time, so never overwrite an entry.
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(self.entry_path(id))
+            {
+                Ok(mut file) => {
+                    file.write_all(content.as_bytes())?;
+                    return Ok(id);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => id += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Save the changes to a queued post. The file is replaced as a whole, so that an
+    /// interrupted save never leaves a truncated entry behind.
+    pub fn save(&self, entry: &QueuedPost) -> io::Result<()> {
+        let path = self.entry_path(entry.id);
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(entry)?)?;
+        fs::rename(temp_path, path)
+    }
+
+    /// Remove a post from the outbox.
+    pub fn remove(&self, id: u32) -> io::Result<()> {
+        fs::remove_file(self.entry_path(id)).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(e.kind(), format!("There is no queued post with ID {}", id))
+            } else {
+                e
+            }
+        })
+    }
+
+    /// Lock the outbox, so that no other `mp` process publishes its posts at the same time.
+    /// The lock is released when the returned guard is dropped.
+    pub fn lock(&self) -> io::Result<OutboxLock> {
+        let path = self.dir.join(".lock");
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => Ok(OutboxLock { path }),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(io::Error::new(
+                e.kind(),
+                format!(
+                    "The outbox is being flushed by another process. If it is not, remove '{}'.",
+                    path.display()
+                ),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn entry_path(&self, id: u32) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+/// Holds the lock on the outbox until it is dropped.
+pub struct OutboxLock {
+    path: PathBuf,
+}
+
+impl Drop for OutboxLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::default_behavior::DefaultBehavior;
+    use mplib::{MicropubService, PostStatus};
+
+    fn test_outbox(name: &str) -> Outbox {
+        let dir =
+            std::env::temp_dir().join(format!("mp-outbox-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Outbox::at(dir).unwrap()
+    }
+
+    fn queued_post(body: &str) -> QueuedPost {
+        let profile = Profile::new(
+            "blog".to_string(),
+            MicropubService::new(
+                "https://example.com/micropub".to_string(),
+                "token".to_string(),
+            ),
+            DefaultBehavior::new(false, false, Vec::new()),
+        );
+        let post = Post::from_body(body.to_string(), PostStatus::Published);
+        QueuedPost::new(post, &profile, new_idempotency_key(), QueueState::Pending)
+    }
+
+    #[test]
+    fn test_add_save_and_remove_entries() {
+        let outbox = test_outbox("entries");
+        assert_eq!(outbox.add(queued_post("First")).unwrap(), 1);
+        assert_eq!(outbox.add(queued_post("Second")).unwrap(), 2);
+
+        let mut entry = outbox.get(1).unwrap();
+        assert_eq!(entry.profile, "blog");
+        assert_eq!(entry.post.body, "First");
+        entry.state = QueueState::Uncertain;
+        outbox.save(&entry).unwrap();
+        assert_eq!(outbox.get(1).unwrap().state, QueueState::Uncertain);
+
+        outbox.remove(1).unwrap();
+        let ids: Vec<u32> = outbox.entries().unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![2]);
+        assert_eq!(outbox.add(queued_post("Third")).unwrap(), 3);
+        assert!(outbox.remove(1).is_err());
+
+        fs::remove_dir_all(&outbox.dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let outbox = test_outbox("lock");
+        let lock = outbox.lock().unwrap();
+        assert!(outbox.lock().is_err());
+        drop(lock);
+        assert!(outbox.lock().is_ok());
+
+        fs::remove_dir_all(&outbox.dir).unwrap();
+    }
+
+    #[test]
+    fn test_summary_truncates_long_bodies() {
+        let entry = queued_post(&format!("\n{}", "word ".repeat(20)));
+        assert_eq!(entry.summary().chars().count(), 50);
+        assert!(entry.summary().ends_with("word…"));
+    }
+}
@@ -0,0 +1,32 @@
+//! Publish a post with the blocking API, without an async runtime.
+//!
+//! Usage: `MP_API_URL=... MP_AUTH_TOKEN=... cargo run --features blocking --example
+//! publish_blocking -- "Hello, world!"`
+
+use mplib::blocking::MicropubClient;
+use mplib::{MicropubService, Post, PostStatus};
+use std::env;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let (Ok(api_url), Ok(auth_token)) = (env::var("MP_API_URL"), env::var("MP_AUTH_TOKEN")) else {
+        eprintln!("Set MP_API_URL and MP_AUTH_TOKEN to the Micropub endpoint and its token.");
+        return ExitCode::FAILURE;
+    };
+    let body = env::args().skip(1).collect::<Vec<_>>().join(" ");
+
+    let service = MicropubService::new(api_url, auth_token);
+    let result = MicropubClient::new(service)
+        .and_then(|client| client.create(Post::from_body(body, PostStatus::Published)));
+
+    match result {
+        Ok(result) => {
+            println!("{}", result.as_string());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
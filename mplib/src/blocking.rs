@@ -0,0 +1,266 @@
+//! A synchronous version of the mplib API, for applications and build scripts that do not use
+//! an async runtime.
+//!
+//! The functions and methods of this module block the current thread until their request
+//! completes. They send the same requests, with the same retries, as their async equivalents,
+//! which they run on a runtime owned by the client.
+//!
+//! They must not be called from async code: doing so panics, as the runtime of the client cannot
+//! run inside another runtime. Async code should use [`crate::MicropubClient`] instead.
+//!
+//! ```no_run
+//! use mplib::blocking::MicropubClient;
+//! use mplib::{MicropubService, Post, PostStatus};
+//!
+//! let service = MicropubService::new(
+//!     "https://example.com/micropub".to_string(),
+//!     "your-auth-token".to_string(),
+//! );
+//! let client = MicropubClient::new(service)?;
+//! let post = Post::from_body("Hello, world!".to_string(), PostStatus::Published);
+//! println!("{}", client.create(post)?.as_string());
+//! # Ok::<(), mplib::PostError>(())
+//! ```
+
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::micropub_service::MicropubService;
+use crate::post::Post;
+use crate::post_error::PostError;
+use crate::post_result::PostResult;
+use crate::server_config::{ServerConfig, SyndicationTarget};
+use crate::token_info::TokenInfo;
+use crate::transport::Transport;
+use crate::update::PostUpdate;
+
+/// A client for a Micropub service that blocks until each request completes.
+///
+/// Like its async equivalent, [`crate::MicropubClient`], the client keeps its connections open
+/// between requests. Cloning a client is cheap, and the clones share their connections and
+/// runtime.
+#[derive(Clone)]
+pub struct MicropubClient {
+    inner: crate::MicropubClient,
+    runtime: Arc<Runtime>,
+}
+
+impl MicropubClient {
+    /// Create a client for the given service, with the timeouts and user agent of the service's
+    /// client configuration.
+    pub fn new(service: MicropubService) -> Result<Self, PostError> {
+        // The HTTP client registers its connections with the runtime it is created in.
+        let runtime = new_runtime()?;
+        let inner = {
+            let _guard = runtime.enter();
+            crate::MicropubClient::new(service)?
+        };
+        Ok(MicropubClient {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Create a client for the given service that sends its requests with the given transport
+    /// (see [`crate::MicropubClient::with_transport`]).
+    pub fn with_transport(
+        service: MicropubService,
+        transport: impl Transport + 'static,
+    ) -> Result<Self, PostError> {
+        Ok(MicropubClient {
+            inner: crate::MicropubClient::with_transport(service, transport),
+            runtime: Arc::new(new_runtime()?),
+        })
+    }
+
+    /// Get the service the client sends its requests to.
+    pub fn service(&self) -> &MicropubService {
+        self.inner.service()
+    }
+
+    /// Publish a Post.
+    pub fn create(&self, post: Post) -> Result<PostResult, PostError> {
+        self.block_on(self.inner.create(post))
+    }
+
+    /// Publish a Post, identifying the request with an idempotency key (see
+    /// [`crate::publish_post_with_idempotency_key`]).
+    pub fn create_with_idempotency_key(
+        &self,
+        post: Post,
+        idempotency_key: &str,
+    ) -> Result<PostResult, PostError> {
+        self.block_on(
+            self.inner
+                .create_with_idempotency_key(post, idempotency_key),
+        )
+    }
+
+    /// Update an existing post, and return its URL (see [`crate::update_post`]).
+    pub fn update(&self, update: PostUpdate) -> Result<String, PostError> {
+        self.block_on(self.inner.update(update))
+    }
+
+    /// Delete the post at the given URL.
+    pub fn delete(&self, url: &str) -> Result<(), PostError> {
+        self.block_on(self.inner.delete(url))
+    }
+
+    /// Restore a previously deleted post at the given URL.
+    pub fn undelete(&self, url: &str) -> Result<(), PostError> {
+        self.block_on(self.inner.undelete(url))
+    }
+
+    /// Send a `q=...` GET query with optional extra parameters and parse the JSON response.
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, PostError> {
+        self.block_on(self.inner.query(query, params))
+    }
+
+    /// Query the service for its configuration and capabilities (`q=config`).
+    pub fn query_config(&self) -> Result<ServerConfig, PostError> {
+        self.block_on(self.inner.query_config())
+    }
+
+    /// Query the service for the categories used on existing posts (`q=category`), optionally
+    /// limited to those matching a search term.
+    pub fn query_categories(&self, search: Option<&str>) -> Result<Vec<String>, PostError> {
+        self.block_on(self.inner.query_categories(search))
+    }
+
+    /// Query the service for the targets posts can be syndicated to (`q=syndicate-to`).
+    pub fn query_syndication_targets(&self) -> Result<Vec<SyndicationTarget>, PostError> {
+        self.block_on(self.inner.query_syndication_targets())
+    }
+
+    /// Upload a file to a Micropub media endpoint, and return the URL of the uploaded file.
+    pub fn upload_media(&self, path: &Path, media_endpoint: &str) -> Result<String, PostError> {
+        self.block_on(self.inner.upload_media(path, media_endpoint))
+    }
+
+    /// Verify the service's access token and report what it grants (see
+    /// [`crate::verify_token`]).
+    pub fn verify_token(&self) -> Result<TokenInfo, PostError> {
+        self.block_on(self.inner.verify_token())
+    }
+
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        self.runtime.block_on(future)
+    }
+}
+
+/// Publish a Post via a Micropub service.
+///
+/// This creates a new client for the request. To publish several posts, use a
+/// [`MicropubClient`] instead.
+pub fn publish_post(post: Post, service: &MicropubService) -> Result<PostResult, PostError> {
+    MicropubClient::new(service.clone())?.create(post)
+}
+
+/// Publish a Post via a Micropub service, identifying the request with an idempotency key (see
+/// [`crate::publish_post_with_idempotency_key`]).
+pub fn publish_post_with_idempotency_key(
+    post: Post,
+    service: &MicropubService,
+    idempotency_key: &str,
+) -> Result<PostResult, PostError> {
+    MicropubClient::new(service.clone())?.create_with_idempotency_key(post, idempotency_key)
+}
+
+/// Update an existing post via a Micropub service, and return its URL.
+pub fn update_post(update: PostUpdate, service: &MicropubService) -> Result<String, PostError> {
+    MicropubClient::new(service.clone())?.update(update)
+}
+
+/// Delete the post at the given URL via a Micropub service.
+pub fn delete_post(url: &str, service: &MicropubService) -> Result<(), PostError> {
+    MicropubClient::new(service.clone())?.delete(url)
+}
+
+/// Restore a previously deleted post at the given URL via a Micropub service.
+pub fn undelete_post(url: &str, service: &MicropubService) -> Result<(), PostError> {
+    MicropubClient::new(service.clone())?.undelete(url)
+}
+
+/// Query a Micropub service for its configuration and capabilities (`q=config`).
+pub fn query_config(service: &MicropubService) -> Result<ServerConfig, PostError> {
+    MicropubClient::new(service.clone())?.query_config()
+}
+
+/// Query a Micropub service for the categories used on existing posts (`q=category`),
+/// optionally limited to those matching a search term.
+pub fn query_categories(
+    service: &MicropubService,
+    search: Option<&str>,
+) -> Result<Vec<String>, PostError> {
+    MicropubClient::new(service.clone())?.query_categories(search)
+}
+
+/// Query a Micropub service for the targets posts can be syndicated to (`q=syndicate-to`).
+pub fn query_syndication_targets(
+    service: &MicropubService,
+) -> Result<Vec<SyndicationTarget>, PostError> {
+    MicropubClient::new(service.clone())?.query_syndication_targets()
+}
+
+/// Upload a file to a Micropub media endpoint, authenticating with the service's token, and
+/// return the URL of the uploaded file.
+pub fn upload_media(
+    path: &Path,
+    media_endpoint: &str,
+    service: &MicropubService,
+) -> Result<String, PostError> {
+    MicropubClient::new(service.clone())?.upload_media(path, media_endpoint)
+}
+
+/// Verify the service's access token and report what it grants (see [`crate::verify_token`]).
+pub fn verify_token(service: &MicropubService) -> Result<TokenInfo, PostError> {
+    MicropubClient::new(service.clone())?.verify_token()
+}
+
+/// Create the single-threaded runtime a client runs its requests on.
+fn new_runtime() -> Result<Runtime, PostError> {
+    Ok(Builder::new_current_thread().enable_all().build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_transport::MockTransport;
+    use crate::post_status::PostStatus;
+    use crate::transport::HttpResponse;
+
+    #[test]
+    fn test_blocking_client_publishes_and_queries() {
+        let transport = MockTransport::new();
+        transport.respond_with(
+            HttpResponse::new(201, "").with_header("Location", "https://example.com/1"),
+        );
+        transport.respond_with(HttpResponse::new(200, r#"{"categories":["rust"]}"#));
+        transport.respond_with(HttpResponse::new(
+            400,
+            r#"{"error":"invalid_request","error_description":"No such post"}"#,
+        ));
+
+        let service = MicropubService::new(
+            "https://example.com/micropub".to_string(),
+            "token".to_string(),
+        );
+        let client = MicropubClient::with_transport(service, transport.clone()).unwrap();
+
+        let post = Post::from_body("Hello".to_string(), PostStatus::Published);
+        assert_eq!(client.create(post).unwrap().url, "https://example.com/1");
+        assert_eq!(client.query_categories(None).unwrap(), vec!["rust"]);
+        assert!(matches!(
+            client.delete("https://example.com/2"),
+            Err(PostError::InvalidRequest(description)) if description == "No such post"
+        ));
+        assert_eq!(transport.requests().len(), 3);
+    }
+}
@@ -0,0 +1,19 @@
+/// Represents whether a Micropub endpoint created a post immediately or accepted it for
+/// asynchronous creation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreationStatus {
+    /// The server responded with `201 Created`; the post exists at the returned URL.
+    Created,
+    /// The server responded with `202 Accepted`; the post will exist at the returned URL once
+    /// the server has finished processing it.
+    Accepted,
+}
+
+impl CreationStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CreationStatus::Created => "created",
+            CreationStatus::Accepted => "accepted",
+        }
+    }
+}
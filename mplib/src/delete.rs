@@ -0,0 +1,44 @@
+use crate::http::Retry;
+use crate::micropub_client::MicropubClient;
+use crate::micropub_service::MicropubService;
+use crate::post_error::PostError;
+use crate::transport::HttpRequest;
+
+/// Delete the post at the given URL via a Micropub service.
+pub async fn delete_post(url: &str, service: &MicropubService) -> Result<(), PostError> {
+    MicropubClient::new(service.clone())?.delete(url).await
+}
+
+/// Restore a previously deleted post at the given URL via a Micropub service.
+pub async fn undelete_post(url: &str, service: &MicropubService) -> Result<(), PostError> {
+    MicropubClient::new(service.clone())?.undelete(url).await
+}
+
+impl MicropubClient {
+    /// Delete the post at the given URL.
+    pub async fn delete(&self, url: &str) -> Result<(), PostError> {
+        self.send_action_request("delete", url).await
+    }
+
+    /// Restore a previously deleted post at the given URL.
+    pub async fn undelete(&self, url: &str) -> Result<(), PostError> {
+        self.send_action_request("undelete", url).await
+    }
+
+    /// Send a request to perform an action on an existing post.
+    async fn send_action_request(&self, action: &str, url: &str) -> Result<(), PostError> {
+        if url.trim().is_empty() {
+            return Err(PostError::InvalidInput(
+                "Post URL cannot be empty".to_string(),
+            ));
+        }
+
+        let service = self.service();
+        let request = HttpRequest::post(&service.api_url)
+            .with_bearer_auth(&service.auth_token)
+            .with_body(service.request_format.encode_action(action, url));
+        // Deleting or restoring a post twice has the same effect as doing it once.
+        self.send(Retry::Idempotent, &request).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+use reqwest::Url;
+
+use crate::http::{ClientConfig, Retry, build_client, send_checked};
+use crate::post_error::PostError;
+use crate::transport::{HttpRequest, ReqwestTransport};
+
+/// Represents the IndieAuth and Micropub endpoints a site advertises.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveredEndpoints {
+    pub micropub: Option<String>,
+    pub media_endpoint: Option<String>,
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: Option<String>,
+    /// Whether the page the endpoints were discovered on is an HTML document.
+    pub is_html: bool,
+}
+
+impl DiscoveredEndpoints {
+    /// Record an endpoint for a `rel` value, unless one was already found for it.
+    fn set_if_missing(&mut self, rel: &str, url: String) {
+        let endpoint = match rel {
+            "micropub" => &mut self.micropub,
+            "media-endpoint" => &mut self.media_endpoint,
+            "authorization_endpoint" => &mut self.authorization_endpoint,
+            "token_endpoint" => &mut self.token_endpoint,
+            _ => return,
+        };
+        if endpoint.is_none() {
+            *endpoint = Some(url);
+        }
+    }
+}
+
+/// Discover the endpoints advertised by the site at the given URL, from its HTTP `Link` headers
+/// and the `<link>` and `<a>` elements with a `rel` attribute in its HTML. Relative URLs are
+/// resolved against the final URL of the page, after redirects.
+///
+/// The URL may omit its scheme, in which case HTTPS is assumed.
+pub async fn discover_endpoints(url: &str) -> Result<DiscoveredEndpoints, PostError> {
+    let url = normalize_site_url(url)?;
+    let config = ClientConfig::default();
+    let transport = ReqwestTransport::new(build_client(&config)?);
+    let response = send_checked(
+        &transport,
+        &config,
+        Retry::Idempotent,
+        &HttpRequest::get(&url),
+    )
+    .await?;
+
+    // Links are relative to the URL of the page after redirects.
+    let base_url = Url::parse(&response.url)
+        .map_err(|_| PostError::InvalidInput(format!("'{}' is not a valid site URL", url)))?;
+
+    let mut endpoints = DiscoveredEndpoints {
+        is_html: response
+            .header("Content-Type")
+            .is_some_and(|content_type| content_type.trim().starts_with("text/html")),
+        ..DiscoveredEndpoints::default()
+    };
+
+    // Link headers take precedence over links in the HTML document.
+    let header_links = response.header_values("Link").flat_map(parse_link_header);
+    for (href, rels) in header_links.chain(parse_html_links(&response.body)) {
+        let Ok(resolved) = base_url.join(&href) else {
+            continue;
+        };
+        for rel in rels {
+            endpoints.set_if_missing(&rel, resolved.to_string());
+        }
+    }
+
+    Ok(endpoints)
+}
+
+/// Parse an HTTP `Link` header into (URL, rel values) pairs.
+fn parse_link_header(header: &str) -> Vec<(String, Vec<String>)> {
+    let mut links = Vec::new();
+    let mut rest = header;
+
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let href = rest[start + 1..start + end].trim().to_string();
+        rest = &rest[start + end + 1..];
+
+        // The parameters of this link run until the next link starts.
+        let params_end = rest.find('<').unwrap_or(rest.len());
+        let params = &rest[..params_end];
+        let rels = params
+            .split(';')
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("rel")
+                    .then(|| value.trim().trim_end_matches(',').trim().trim_matches('"'))
+            })
+            .flat_map(|value| value.split_whitespace().map(str::to_string))
+            .collect();
+
+        links.push((href, rels));
+    }
+
+    links
+}
+
+/// Parse the `<link>` and `<a>` elements of an HTML document into (URL, rel values) pairs.
+fn parse_html_links(html: &str) -> Vec<(String, Vec<String>)> {
+    let mut links = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        let tag_start = &rest[start + 1..];
+        let end = tag_start.find('>').unwrap_or(tag_start.len());
+        let tag = &tag_start[..end];
+        rest = &tag_start[end..];
+
+        let mut parts = tag.splitn(2, |c: char| c.is_ascii_whitespace());
+        let name = parts.next().unwrap_or_default();
+        if !name.eq_ignore_ascii_case("link") && !name.eq_ignore_ascii_case("a") {
+            continue;
+        }
+
+        let attributes = parse_attributes(parts.next().unwrap_or_default());
+        let attribute = |wanted: &str| {
+            attributes
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+                .map(|(_, value)| value.clone())
+        };
+        if let (Some(href), Some(rel)) = (attribute("href"), attribute("rel")) {
+            let rels = rel.split_whitespace().map(str::to_string).collect();
+            links.push((href, rels));
+        }
+    }
+
+    links
+}
+
+/// Parse the attributes of an HTML tag into (name, value) pairs. Values may be double-quoted,
+/// single-quoted, or unquoted.
+fn parse_attributes(attributes: &str) -> Vec<(String, String)> {
+    let mut parsed = Vec::new();
+    let mut rest = attributes.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].trim_end_matches('/').to_string();
+        rest = rest[name_end..].trim_start();
+
+        let value = if let Some(after_equals) = rest.strip_prefix('=') {
+            let after_equals = after_equals.trim_start();
+            let (value, remainder) = match after_equals.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_equals[1..];
+                    let close = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..close], inner.get(close + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let close = after_equals
+                        .find(|c: char| c.is_ascii_whitespace())
+                        .unwrap_or(after_equals.len());
+                    (&after_equals[..close], &after_equals[close..])
+                }
+            };
+            rest = remainder.trim_start();
+            value.to_string()
+        } else {
+            String::new()
+        };
+
+        if !name.is_empty() {
+            parsed.push((name, value));
+        }
+    }
+
+    parsed
+}
+
+/// Normalize a URL entered by a user to identify a site: add `https://` if no scheme is given
+/// and `/` if no path is given.
+pub(crate) fn normalize_site_url(url: &str) -> Result<String, PostError> {
+    let url = url.trim();
+    let with_scheme = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+
+    match Url::parse(&with_scheme) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {
+            Ok(parsed.to_string())
+        }
+        _ => Err(PostError::InvalidInput(format!(
+            "'{}' is not a valid site URL",
+            url
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_link_header_multiple_links_and_rels() {
+        let header = r#"<https://example.com/micropub>; rel="micropub", </auth>; rel="authorization_endpoint token_endpoint""#;
+        assert_eq!(
+            parse_link_header(header),
+            vec![
+                (
+                    "https://example.com/micropub".to_string(),
+                    vec!["micropub".to_string()]
+                ),
+                (
+                    "/auth".to_string(),
+                    vec![
+                        "authorization_endpoint".to_string(),
+                        "token_endpoint".to_string()
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_html_links() {
+        let html = r#"<html><head>
+            <LINK REL="micropub" HREF="/micropub">
+            <link href='https://example.com/auth' rel='authorization_endpoint' />
+            <link rel=token_endpoint href=https://example.com/token>
+            <link rel="stylesheet" href="/style.css">
+            <a rel="me" href="https://mastodon.example/@me">Mastodon</a>
+            </head></html>"#;
+        let links = parse_html_links(html);
+        assert_eq!(links.len(), 5);
+        assert_eq!(
+            links[0],
+            ("/micropub".to_string(), vec!["micropub".to_string()])
+        );
+        assert_eq!(links[1].0, "https://example.com/auth");
+        assert_eq!(links[2].0, "https://example.com/token");
+        assert_eq!(links[4].1, vec!["me".to_string()]);
+    }
+
+    #[test]
+    fn test_first_endpoint_wins() {
+        let mut endpoints = DiscoveredEndpoints::default();
+        endpoints.set_if_missing("micropub", "https://first.example/".to_string());
+        endpoints.set_if_missing("micropub", "https://second.example/".to_string());
+        assert_eq!(
+            endpoints.micropub.as_deref(),
+            Some("https://first.example/")
+        );
+    }
+
+    /// Serve a single response with the given headers and body, and return the URL of the
+    /// server.
+    async fn serve_once(headers: &'static str, body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let site_url = format!(
+            "http://127.0.0.1:{}/",
+            listener.local_addr().unwrap().port()
+        );
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                headers,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        site_url
+    }
+
+    #[tokio::test]
+    async fn test_discover_endpoints_resolves_relative_urls() {
+        let site_url = serve_once(
+            "Link: </micropub>; rel=\"micropub\"\r\n",
+            r#"<link rel="micropub" href="/ignored"><link rel="media-endpoint" href="media">"#,
+        )
+        .await;
+
+        let endpoints = discover_endpoints(&site_url).await.unwrap();
+        assert_eq!(endpoints.micropub, Some(format!("{}micropub", site_url)));
+        assert_eq!(endpoints.media_endpoint, Some(format!("{}media", site_url)));
+        assert_eq!(endpoints.token_endpoint, None);
+        assert!(!endpoints.is_html);
+    }
+
+    #[tokio::test]
+    async fn test_discover_endpoints_on_html_page_without_links() {
+        let site_url = serve_once(
+            "Content-Type: text/html; charset=utf-8\r\n",
+            "<html><head><title>Home</title></head></html>",
+        )
+        .await;
+
+        let endpoints = discover_endpoints(&site_url).await.unwrap();
+        assert_eq!(endpoints.micropub, None);
+        assert!(endpoints.is_html);
+    }
+
+    #[test]
+    fn test_normalize_site_url() {
+        assert_eq!(
+            normalize_site_url("example.com").unwrap(),
+            "https://example.com/"
+        );
+        assert_eq!(
+            normalize_site_url("http://example.com/blog").unwrap(),
+            "http://example.com/blog"
+        );
+        assert!(normalize_site_url("ftp://example.com/").is_err());
+    }
+}
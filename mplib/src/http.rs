@@ -0,0 +1,371 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::ClientBuilder;
+use serde::Deserialize;
+use serde_json::from_str;
+
+use crate::post_error::PostError;
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+
+/// Settings of the HTTP client that sends requests to a service: its timeouts, its user agent,
+/// and how failed requests are retried.
+///
+/// Only requests that can safely be sent twice are retried after they may have reached the
+/// server: queries, deletions, and updates that do not add values. New posts are only treated
+/// as such if they carry an idempotency key and `honors_idempotency_key` is set. Other requests
+/// are only retried when no connection could be made, so they were certainly not sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// How long to wait for a connection to the server.
+    pub connect_timeout: Duration,
+    /// How long to wait for a request to complete, including sending its body and receiving
+    /// the response.
+    pub request_timeout: Duration,
+    /// How many times a failed request is retried.
+    pub max_retries: u32,
+    /// The delay before the first retry. It doubles with every further retry.
+    pub initial_backoff: Duration,
+    /// The longest delay before a retry. A server that asks to wait longer with `Retry-After`
+    /// is not retried.
+    pub max_backoff: Duration,
+    /// The `User-Agent` header sent with every request.
+    pub user_agent: String,
+    /// Whether the server recognizes a post sent again with the same `Idempotency-Key` header
+    /// and does not create it twice. Micropub does not define the header, so this is off by
+    /// default, and posts are only retried when they were certainly not sent.
+    pub honors_idempotency_key: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(30),
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            user_agent: concat!("mplib/", env!("CARGO_PKG_VERSION")).to_string(),
+            honors_idempotency_key: false,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// The delay before the retry that follows the given number of retries.
+    fn backoff(&self, retries: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max_backoff)
+    }
+}
+
+/// Whether a request can safely be sent again after it may have reached the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Retry {
+    /// Sending the request twice has the same effect as sending it once.
+    Idempotent,
+    /// The request is only sent again if it was certainly not sent.
+    UnlessSent,
+}
+
+/// Build an HTTP client with the given timeouts.
+pub(crate) fn build_client(config: &ClientConfig) -> Result<reqwest::Client, PostError> {
+    ClientBuilder::new()
+        .connect_timeout(config.connect_timeout)
+        .timeout(config.request_timeout)
+        .user_agent(&config.user_agent)
+        .build()
+        .map_err(PostError::Network)
+}
+
+/// Send a request with the given transport, retrying it with exponential backoff as allowed by
+/// `retry` and the client configuration.
+///
+/// Idempotent requests are retried after network errors and after responses that report a
+/// temporary condition (429, 502, 503, and 504), waiting as long as the `Retry-After` header of
+/// the response asks, if it has one. Other requests are only retried after connection errors.
+pub(crate) async fn send_with_retries(
+    transport: &dyn Transport,
+    config: &ClientConfig,
+    retry: Retry,
+    request: &HttpRequest,
+) -> Result<HttpResponse, PostError> {
+    let mut retries = 0;
+    loop {
+        let result = transport.send(request).await;
+
+        let delay = if retries >= config.max_retries {
+            None
+        } else {
+            match &result {
+                Err(e) if e.is_connect_failure() => Some(config.backoff(retries)),
+                Err(e) if retry == Retry::Idempotent && e.is_transport_failure() => {
+                    Some(config.backoff(retries))
+                }
+                Ok(response)
+                    if retry == Retry::Idempotent && is_temporary_failure(response.status) =>
+                {
+                    match retry_after(response) {
+                        Some(delay) if delay > config.max_backoff => None,
+                        Some(delay) => Some(delay),
+                        None => Some(config.backoff(retries)),
+                    }
+                }
+                _ => None,
+            }
+        };
+
+        match delay {
+            Some(delay) => {
+                tokio::time::sleep(delay).await;
+                retries += 1;
+            }
+            None => return result,
+        }
+    }
+}
+
+/// Send a request and convert an unsuccessful response into a PostError (see
+/// [`send_with_retries`]).
+pub(crate) async fn send_checked(
+    transport: &dyn Transport,
+    config: &ClientConfig,
+    retry: Retry,
+    request: &HttpRequest,
+) -> Result<HttpResponse, PostError> {
+    let response = send_with_retries(transport, config, retry, request).await?;
+    if !response.is_success() {
+        return Err(api_error_from_response(response.status, &response.body));
+    }
+    Ok(response)
+}
+
+/// Check if a response status reports a condition that may go away if the request is sent
+/// again later.
+fn is_temporary_failure(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503 | 504)
+}
+
+/// Get how long the server asks to wait before the request is sent again, from the
+/// `Retry-After` header of a response, given in seconds or as an HTTP date.
+fn retry_after(response: &HttpResponse) -> Option<Duration> {
+    let value = response.header("Retry-After")?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = parse_http_date(value)?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Parse a date in the format HTTP uses, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| name == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let time: Vec<i64> = time
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = time.as_slice() else {
+        return None;
+    };
+
+    // Count the days since the Unix epoch in the proleptic Gregorian calendar, with years
+    // starting in March so that leap days come last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hours * 3_600 + minutes * 60 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+/// Get the value of the `Location` header of a response, if present.
+pub(crate) fn location_header(response: &HttpResponse) -> Option<String> {
+    response.header("Location").map(str::to_string)
+}
+
+/// Convert an unsuccessful response into a PostError.
+///
+/// Micropub servers report errors as JSON objects with an `error` code defined by the
+/// specification. Known codes are mapped to dedicated variants so callers can, for example, tell
+/// a token that lacks a scope apart from a server that rejects the request. Responses that are not
+/// Micropub error objects are reported with their HTTP status.
+pub(crate) fn api_error_from_response(status: u16, text: &str) -> PostError {
+    let Ok(api_error) = from_str::<ApiPostError>(text) else {
+        return PostError::Http {
+            status,
+            body: text.trim().to_string(),
+        };
+    };
+
+    let description = api_error
+        .error_description
+        .unwrap_or_else(|| api_error.error.clone());
+
+    match api_error.error.as_str() {
+        "unauthorized" => PostError::Unauthorized(description),
+        "forbidden" => PostError::Forbidden(description),
+        "insufficient_scope" => PostError::InsufficientScope(description),
+        "invalid_request" => PostError::InvalidRequest(description),
+        _ => PostError::Api {
+            error: api_error.error,
+            description,
+        },
+    }
+}
+
+/// Represents the expected error response from the Micropub API upon a failed request.
+#[derive(Deserialize)]
+struct ApiPostError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_transport::MockTransport;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let config = ClientConfig {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..ClientConfig::default()
+        };
+        assert_eq!(config.backoff(0), Duration::from_secs(1));
+        assert_eq!(config.backoff(2), Duration::from_secs(4));
+        assert_eq!(config.backoff(3), Duration::from_secs(5));
+        assert_eq!(config.backoff(40), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_709_164_800))
+        );
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("soon"), None);
+    }
+
+    fn fast_retries() -> ClientConfig {
+        ClientConfig {
+            initial_backoff: Duration::from_millis(1),
+            ..ClientConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_request_is_retried_after_retry_after() {
+        let transport = MockTransport::new();
+        transport.respond_with(HttpResponse::new(503, "").with_header("Retry-After", "0"));
+        transport.respond_with(HttpResponse::new(429, ""));
+        transport.respond_with(HttpResponse::new(200, ""));
+
+        let request = HttpRequest::get("https://example.com/micropub");
+        let response = send_with_retries(&transport, &fast_retries(), Retry::Idempotent, &request)
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_request_that_may_have_been_processed_is_not_retried() {
+        let transport = MockTransport::new();
+        transport.respond_with(HttpResponse::new(503, "").with_header("Retry-After", "0"));
+        transport.fail_with(PostError::ConnectionLost("reset".to_string()));
+
+        let request = HttpRequest::post("https://example.com/micropub");
+        let response = send_with_retries(&transport, &fast_retries(), Retry::UnlessSent, &request)
+            .await
+            .unwrap();
+        assert_eq!(response.status, 503);
+
+        let result =
+            send_with_retries(&transport, &fast_retries(), Retry::UnlessSent, &request).await;
+        assert!(matches!(result, Err(PostError::ConnectionLost(_))));
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_that_was_not_sent_is_retried_until_the_limit() {
+        let transport = MockTransport::new();
+        for _ in 0..3 {
+            transport.fail_with(PostError::ConnectFailed("refused".to_string()));
+        }
+
+        let request = HttpRequest::post("https://example.com/micropub");
+        let result =
+            send_with_retries(&transport, &fast_retries(), Retry::UnlessSent, &request).await;
+        assert!(matches!(result, Err(PostError::ConnectFailed(_))));
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_long_retry_after_is_not_waited_for() {
+        let transport = MockTransport::new();
+        transport.respond_with(HttpResponse::new(429, "").with_header("Retry-After", "3600"));
+
+        let request = HttpRequest::get("https://example.com/micropub");
+        let response = send_with_retries(&transport, &fast_retries(), Retry::Idempotent, &request)
+            .await
+            .unwrap();
+        assert_eq!(response.status, 429);
+    }
+
+    #[test]
+    fn test_api_error_insufficient_scope() {
+        let text = r#"{"error":"insufficient_scope","error_description":"Missing delete scope"}"#;
+        let error = api_error_from_response(403, text);
+        assert!(
+            matches!(error, PostError::InsufficientScope(description) if description == "Missing delete scope")
+        );
+    }
+
+    #[test]
+    fn test_api_error_invalid_request_without_description() {
+        let text = r#"{"error":"invalid_request"}"#;
+        let error = api_error_from_response(400, text);
+        assert!(
+            matches!(error, PostError::InvalidRequest(description) if description == "invalid_request")
+        );
+    }
+
+    #[test]
+    fn test_api_error_unknown_code() {
+        let text = r#"{"error":"server_error","error_description":"Oops"}"#;
+        let error = api_error_from_response(500, text);
+        assert!(matches!(error, PostError::Api { error, .. } if error == "server_error"));
+    }
+
+    #[test]
+    fn test_api_error_non_json_body() {
+        let error = api_error_from_response(502, "<html>Bad gateway</html>\n");
+        assert!(matches!(
+            error,
+            PostError::Http { status: 502, body } if body == "<html>Bad gateway</html>"
+        ));
+    }
+}
@@ -0,0 +1,463 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::discovery::{discover_endpoints, normalize_site_url};
+use crate::http::{ClientConfig, api_error_from_response, build_client};
+use crate::post_error::PostError;
+use crate::transport::{HttpBody, HttpRequest, ReqwestTransport, Transport};
+
+/// The scopes requested when logging in, which cover everything mplib can do.
+pub const DEFAULT_SCOPE: &str = "create update delete media";
+
+/// Represents an IndieAuth login in progress, using the authorization code flow with PKCE and a
+/// loopback redirect listener on localhost.
+///
+/// Start a session with [`LoginSession::start`], send the user to
+/// [`LoginSession::authorization_url`] in a browser, then call [`LoginSession::finish`] to wait
+/// for the authorization server to redirect back and exchange the code for a token.
+pub struct LoginSession {
+    me: String,
+    client_id: String,
+    redirect_uri: String,
+    state: String,
+    code_verifier: String,
+    authorization_url: String,
+    authorization_endpoint: String,
+    micropub_endpoint: String,
+    token_endpoint: String,
+    listener: TcpListener,
+}
+
+/// Represents the outcome of a successful IndieAuth login.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginResult {
+    /// The canonical URL of the user returned by the token endpoint. It is either on the same
+    /// site as the URL the user logged in with, or advertises the same authorization endpoint.
+    pub me: String,
+    pub access_token: String,
+    /// The scopes granted by the user, separated by spaces.
+    pub scope: Option<String>,
+    pub micropub_endpoint: String,
+    pub token_endpoint: String,
+}
+
+impl LoginSession {
+    /// Discover the endpoints of the site at `me` and prepare an authorization request.
+    ///
+    /// Without a `client_id`, the URL of the loopback listener is used as the client ID, so that
+    /// the redirect URI is on the same host as the client ID, as authorization servers expect.
+    pub async fn start(me: &str, client_id: Option<&str>, scope: &str) -> Result<Self, PostError> {
+        let me = normalize_site_url(me)?;
+        let endpoints = discover_endpoints(&me).await?;
+
+        let micropub_endpoint = endpoints
+            .micropub
+            .ok_or_else(|| PostError::MissingEndpoint("micropub endpoint".to_string()))?;
+        let authorization_endpoint = endpoints
+            .authorization_endpoint
+            .ok_or_else(|| PostError::MissingEndpoint("authorization_endpoint".to_string()))?;
+        let token_endpoint = endpoints
+            .token_endpoint
+            .ok_or_else(|| PostError::MissingEndpoint("token_endpoint".to_string()))?;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let loopback_url = format!("http://127.0.0.1:{}/", listener.local_addr()?.port());
+        let redirect_uri = format!("{}callback", loopback_url);
+        let client_id = client_id.map_or(loopback_url, str::to_string);
+
+        let state = random_token()?;
+        let code_verifier = random_token()?;
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let mut authorization_url = Url::parse(&authorization_endpoint).map_err(|_| {
+            PostError::InvalidInput(format!(
+                "'{}' is not a valid authorization endpoint",
+                authorization_endpoint
+            ))
+        })?;
+        authorization_url
+            .query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("me", &me)
+            .append_pair("client_id", &client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("scope", scope);
+
+        Ok(LoginSession {
+            me,
+            client_id,
+            redirect_uri,
+            state,
+            code_verifier,
+            authorization_url: authorization_url.to_string(),
+            authorization_endpoint,
+            micropub_endpoint,
+            token_endpoint,
+            listener,
+        })
+    }
+
+    /// The URL the user must open in a browser to approve the login.
+    pub fn authorization_url(&self) -> &str {
+        &self.authorization_url
+    }
+
+    /// Wait for the authorization server to redirect the browser back to the loopback listener,
+    /// then exchange the authorization code for an access token.
+    pub async fn finish(self) -> Result<LoginResult, PostError> {
+        let code = self.wait_for_code().await?;
+
+        // Authorization codes can only be used once, so the request is not retried.
+        let transport = ReqwestTransport::new(build_client(&ClientConfig::default())?);
+        let request = HttpRequest::post(&self.token_endpoint)
+            .with_header("Accept", "application/json")
+            .with_body(HttpBody::Form(
+                [
+                    ("grant_type", "authorization_code"),
+                    ("code", code.as_str()),
+                    ("client_id", self.client_id.as_str()),
+                    ("redirect_uri", self.redirect_uri.as_str()),
+                    ("code_verifier", self.code_verifier.as_str()),
+                ]
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ));
+        let response = transport.send(&request).await?;
+
+        if !response.is_success() {
+            return Err(api_error_from_response(response.status, &response.body));
+        }
+
+        let token: TokenResponse = serde_json::from_str(&response.body)?;
+        let me = match token.me {
+            Some(returned) => self.verify_me(&returned).await?,
+            None => self.me,
+        };
+        Ok(LoginResult {
+            me,
+            access_token: token.access_token,
+            scope: token.scope,
+            micropub_endpoint: self.micropub_endpoint,
+            token_endpoint: self.token_endpoint,
+        })
+    }
+
+    /// Check that the profile URL returned by the token endpoint belongs to the user who logged
+    /// in: it must be on the same site as the URL they entered, or advertise the same
+    /// authorization endpoint.
+    async fn verify_me(&self, returned: &str) -> Result<String, PostError> {
+        let returned = normalize_site_url(returned)?;
+        let same_site = match (Url::parse(&returned), Url::parse(&self.me)) {
+            (Ok(returned), Ok(entered)) => returned.origin() == entered.origin(),
+            _ => false,
+        };
+        if same_site {
+            return Ok(returned);
+        }
+
+        let endpoints = discover_endpoints(&returned).await?;
+        if endpoints.authorization_endpoint.as_deref() == Some(self.authorization_endpoint.as_str())
+        {
+            Ok(returned)
+        } else {
+            Err(PostError::Authorization(format!(
+                "the token endpoint returned the profile URL '{}', which does not use the \
+                 authorization endpoint of '{}'",
+                returned, self.me
+            )))
+        }
+    }
+
+    /// Accept connections on the loopback listener until the browser is redirected to it with
+    /// an authorization code or an error.
+    async fn wait_for_code(&self) -> Result<String, PostError> {
+        loop {
+            let (mut stream, _) = self.listener.accept().await?;
+            let Some(path) = read_request_path(&mut stream).await? else {
+                continue;
+            };
+
+            // Browsers may also ask for other paths, such as /favicon.ico.
+            let url = Url::parse(&format!("http://127.0.0.1{}", path))
+                .map_err(|e| PostError::Authorization(e.to_string()))?;
+            if url.path() != "/callback" {
+                respond(&mut stream, "404 Not Found", "Not found.").await?;
+                continue;
+            }
+
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            };
+
+            if let Some(error) = param("error") {
+                respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "Login failed. You can close this window.",
+                )
+                .await?;
+                let description = param("error_description").unwrap_or_default();
+                return Err(PostError::Authorization(
+                    format!("{} {}", error, description).trim().to_string(),
+                ));
+            }
+
+            if param("state").as_deref() != Some(self.state.as_str()) {
+                respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "Login failed. You can close this window.",
+                )
+                .await?;
+                return Err(PostError::Authorization(
+                    "the state returned by the authorization server does not match".to_string(),
+                ));
+            }
+
+            let Some(code) = param("code") else {
+                respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "Login failed. You can close this window.",
+                )
+                .await?;
+                return Err(PostError::Authorization(
+                    "the authorization server did not return a code".to_string(),
+                ));
+            };
+
+            respond(
+                &mut stream,
+                "200 OK",
+                "Login successful. You can close this window and return to the terminal.",
+            )
+            .await?;
+            return Ok(code);
+        }
+    }
+}
+
+/// Represents the response of a token endpoint to an authorization code exchange.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    me: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Generate a random, URL-safe string suitable for `state` and PKCE code verifiers.
+fn random_token() -> Result<String, PostError> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Read the head of an HTTP request and return the path of its request line, if it has one.
+async fn read_request_path(stream: &mut TcpStream) -> Result<Option<String>, PostError> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") && buffer.len() < 16 * 1024 {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&buffer);
+    let path = head
+        .lines()
+        .next()
+        .and_then(|request_line| request_line.split_whitespace().nth(1))
+        .map(str::to_string);
+    Ok(path)
+}
+
+/// Write a minimal HTML response to the browser.
+async fn respond(stream: &mut TcpStream, status: &str, message: &str) -> Result<(), PostError> {
+    let body = format!(
+        "<!DOCTYPE html><html><head><title>mp</title></head><body><p>{}</p></body></html>",
+        message
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A stand-in site and authorization server that approves every authorization request. The
+    /// token endpoint returns a profile URL on `me_host`, with the port of the site.
+    async fn start_stand_in_server(me_host: &'static str) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let code_challenge = Arc::new(Mutex::new(String::new()));
+
+        let site_url = base_url.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let (request_line, body) = read_request(&mut stream).await;
+                let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+                let url = Url::parse(&format!("{}{}", site_url, path)).unwrap();
+                let param = |params: &str, name: &str| {
+                    Url::parse(&format!("http://localhost/?{}", params))
+                        .unwrap()
+                        .query_pairs()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.into_owned())
+                        .unwrap_or_default()
+                };
+
+                let response = match url.path() {
+                    "/" => format!(
+                        "HTTP/1.1 200 OK\r\nLink: <{}/micropub>; rel=\"micropub\"\r\nContent-Length: 101\r\n\r\n{:<101}",
+                        site_url,
+                        r#"<link rel="authorization_endpoint" href="/auth"><link rel="token_endpoint" href="/token">"#
+                    ),
+                    "/auth" => {
+                        let query = url.query().unwrap_or_default();
+                        *code_challenge.lock().unwrap() = param(query, "code_challenge");
+                        format!(
+                            "HTTP/1.1 302 Found\r\nLocation: {}?code=secret-code&state={}\r\nContent-Length: 0\r\n\r\n",
+                            param(query, "redirect_uri"),
+                            param(query, "state")
+                        )
+                    }
+                    "/token" => {
+                        let verifier = param(&body, "code_verifier");
+                        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+                        if param(&body, "code") == "secret-code"
+                            && challenge == *code_challenge.lock().unwrap()
+                        {
+                            let me = site_url.replace("127.0.0.1", me_host);
+                            let json = format!(
+                                r#"{{"access_token":"issued-token","me":"{}/","scope":"create media"}}"#,
+                                me
+                            );
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                                json.len(),
+                                json
+                            )
+                        } else {
+                            let json = r#"{"error":"invalid_grant"}"#;
+                            format!(
+                                "HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\n\r\n{}",
+                                json.len(),
+                                json
+                            )
+                        }
+                    }
+                    _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        base_url
+    }
+
+    /// Read a request and return its request line and body.
+    async fn read_request(stream: &mut TcpStream) -> (String, String) {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            buffer.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&buffer).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let content_length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if body.len() >= content_length || read == 0 {
+                    let request_line = head.lines().next().unwrap().to_string();
+                    return (request_line, body.to_string());
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_against_stand_in_server() {
+        let site_url = start_stand_in_server("127.0.0.1").await;
+
+        let session = LoginSession::start(&site_url, None, DEFAULT_SCOPE)
+            .await
+            .unwrap();
+        assert!(
+            session
+                .authorization_url()
+                .starts_with(&format!("{}/auth?response_type=code", site_url))
+        );
+        // Without a client ID, the loopback listener identifies the client.
+        assert!(
+            session
+                .authorization_url()
+                .contains("client_id=http%3A%2F%2F127.0.0.1%3A")
+        );
+
+        // Play the part of the browser: follow the redirect back to the loopback listener.
+        let authorization_url = session.authorization_url().to_string();
+        let browser = tokio::spawn(async move { reqwest::get(authorization_url).await });
+
+        let result = session.finish().await.unwrap();
+        assert_eq!(result.access_token, "issued-token");
+        assert_eq!(result.me, format!("{}/", site_url));
+        assert_eq!(result.scope.as_deref(), Some("create media"));
+        assert_eq!(result.micropub_endpoint, format!("{}/micropub", site_url));
+        assert_eq!(result.token_endpoint, format!("{}/token", site_url));
+
+        let browser_response = browser.await.unwrap().unwrap();
+        assert!(browser_response.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_login_rejects_profile_url_with_other_authorization_endpoint() {
+        // The site at localhost advertises its endpoints on localhost, not 127.0.0.1.
+        let site_url = start_stand_in_server("localhost").await;
+
+        let session =
+            LoginSession::start(&site_url, Some("https://client.example/"), DEFAULT_SCOPE)
+                .await
+                .unwrap();
+        assert!(
+            session
+                .authorization_url()
+                .contains("client_id=https%3A%2F%2Fclient.example%2F")
+        );
+        let authorization_url = session.authorization_url().to_string();
+        tokio::spawn(async move { reqwest::get(authorization_url).await });
+
+        let result = session.finish().await;
+        assert!(matches!(result, Err(PostError::Authorization(_))));
+    }
+}
@@ -4,28 +4,81 @@
 //!
 //! ## Example
 //!
-//!! ```rust
-//! use mplib::{MicropubService, Post, publish_post};
+//! ```no_run
+//! use mplib::{MicropubService, Post, PostStatus, publish_post};
 //!
-//! fn main() {
-//!     let service = MicropubService::new("https://example.com/micropub", "your_access_token");
-//!     let post = Post::new("Hello, world!", None);
-//!     match publish_post(&service, &post) {
-//!         Ok(result) => println!("Post published successfully: {:?}", result),
+//! #[tokio::main]
+//! async fn main() {
+//!     let service = MicropubService::new(
+//!         "https://example.com/micropub".to_string(),
+//!         "your_access_token".to_string(),
+//!     );
+//!     let post = Post::from_body("Hello, world!".to_string(), PostStatus::Published);
+//!     match publish_post(post, &service).await {
+//!         Ok(result) => println!("{}", result.as_string()),
 //!         Err(e) => eprintln!("Failed to publish post: {}", e),
 //!     }
 //! }
 //! ```
+//!
+//! ## Features
+//!
+//! - `blocking`: adds the `mplib::blocking` module, with synchronous equivalents of the functions
+//!   and client of this crate, for programs that do not use an async runtime.
+//! - `testing`: exports `MockTransport`, for testing code that uses mplib without a Micropub
+//!   server.
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod creation_status;
+mod delete;
+mod discovery;
+mod http;
+mod indieauth;
+mod media;
+mod micropub_client;
 mod micropub_service;
+#[cfg(any(test, feature = "testing"))]
+mod mock_transport;
+mod photo;
 mod post;
+mod post_error;
 mod post_result;
 mod post_status;
+mod properties;
 mod publish;
+mod query;
+mod request_format;
+mod server_config;
+mod token_info;
+mod transport;
+mod update;
+mod verify;
 
 // Re-export the main types and functions for library users
+pub use crate::creation_status::CreationStatus;
+pub use crate::delete::{delete_post, undelete_post};
+pub use crate::discovery::{DiscoveredEndpoints, discover_endpoints};
+pub use crate::http::ClientConfig;
+pub use crate::indieauth::{DEFAULT_SCOPE, LoginResult, LoginSession};
+pub use crate::media::upload_media;
+pub use crate::micropub_client::MicropubClient;
 pub use crate::micropub_service::MicropubService;
+#[cfg(feature = "testing")]
+pub use crate::mock_transport::MockTransport;
+pub use crate::photo::Photo;
 pub use crate::post::Post;
+pub use crate::post_error::PostError;
 pub use crate::post_result::PostResult;
 pub use crate::post_status::PostStatus;
-pub use crate::publish::publish_post;
+pub use crate::properties::{Properties, PropertyValue};
+pub use crate::publish::{publish_post, publish_post_with_idempotency_key};
+pub use crate::query::{query_categories, query_config, query_syndication_targets};
+pub use crate::request_format::RequestFormat;
+pub use crate::server_config::{Destination, PostType, ServerConfig, SyndicationTarget};
+pub use crate::token_info::TokenInfo;
+pub use crate::transport::{
+    HttpBody, HttpMethod, HttpRequest, HttpResponse, ReqwestTransport, Transport, TransportFuture,
+};
+pub use crate::update::{PostUpdate, update_post};
+pub use crate::verify::verify_token;
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use crate::http::{Retry, location_header};
+use crate::micropub_client::MicropubClient;
+use crate::micropub_service::MicropubService;
+use crate::post_error::PostError;
+use crate::transport::{HttpBody, HttpRequest};
+
+/// Upload a file to a Micropub media endpoint, authenticating with the service's token.
+///
+/// The media endpoint is usually discovered with a `q=config` query (see
+/// [`crate::ServerConfig::media_endpoint`]). Returns the URL of the uploaded file.
+pub async fn upload_media(
+    path: &Path,
+    media_endpoint: &str,
+    service: &MicropubService,
+) -> Result<String, PostError> {
+    MicropubClient::new(service.clone())?
+        .upload_media(path, media_endpoint)
+        .await
+}
+
+impl MicropubClient {
+    /// Upload a file to a Micropub media endpoint, and return the URL of the uploaded file (see
+    /// [`upload_media`]).
+    pub async fn upload_media(
+        &self,
+        path: &Path,
+        media_endpoint: &str,
+    ) -> Result<String, PostError> {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                PostError::InvalidInput(format!("'{}' is not a valid file path", path.display()))
+            })?
+            .to_string();
+        let content_type = mime_guess::from_path(path).first_or_octet_stream();
+        // Read on a blocking thread, so that large files do not stall the runtime.
+        let bytes = tokio::fs::read(path).await?;
+
+        let service = self.service();
+        let request = HttpRequest::post(media_endpoint)
+            .with_bearer_auth(&service.auth_token)
+            .with_body(HttpBody::Multipart {
+                field: "file".to_string(),
+                file_name,
+                content_type: content_type.to_string(),
+                bytes,
+            });
+        // Uploading a file twice stores it twice, so the upload is only retried if it was not
+        // sent.
+        let response = self.send(Retry::UnlessSent, &request).await?;
+
+        location_header(&response).ok_or(PostError::MissingLocation)
+    }
+}
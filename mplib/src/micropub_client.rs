@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use crate::http::{Retry, build_client, send_checked};
+use crate::micropub_service::MicropubService;
+use crate::post_error::PostError;
+use crate::transport::{HttpRequest, HttpResponse, ReqwestTransport, Transport};
+
+/// A client for a Micropub service.
+///
+/// The client keeps its HTTP connections open between requests, so sending many requests with
+/// the same client is faster than calling the free functions of this crate, which create a new
+/// client for every request. Cloning a client is cheap, and the clones share their connections.
+///
+/// The requests themselves are sent by the methods defined next to each operation: creating
+/// posts, updating, deleting, and restoring them, querying the service, uploading media, and
+/// verifying the token. They all go through the client's [`Transport`].
+#[derive(Clone)]
+pub struct MicropubClient {
+    transport: Arc<dyn Transport>,
+    service: MicropubService,
+}
+
+impl MicropubClient {
+    /// Create a client for the given service, with the timeouts and user agent of the service's
+    /// client configuration.
+    pub fn new(service: MicropubService) -> Result<Self, PostError> {
+        let http = build_client(&service.client_config)?;
+        Ok(MicropubClient::with_http_client(service, http))
+    }
+
+    /// Create a client for the given service that sends its requests with an HTTP client built
+    /// by the caller, for example one that uses a proxy or sends default headers.
+    ///
+    /// The timeouts and user agent of the service's client configuration are not applied to the
+    /// HTTP client, but its retry settings are.
+    pub fn with_http_client(service: MicropubService, http: reqwest::Client) -> Self {
+        MicropubClient::with_transport(service, ReqwestTransport::new(http))
+    }
+
+    /// Create a client for the given service that sends its requests with the given transport.
+    ///
+    /// The retry settings of the service's client configuration are applied to the requests;
+    /// its timeouts and user agent are up to the transport.
+    pub fn with_transport(service: MicropubService, transport: impl Transport + 'static) -> Self {
+        MicropubClient {
+            transport: Arc::new(transport),
+            service,
+        }
+    }
+
+    /// Get the service the client sends its requests to.
+    pub fn service(&self) -> &MicropubService {
+        &self.service
+    }
+
+    /// Send a request, retrying it as allowed by `retry`, and convert an unsuccessful response
+    /// into a PostError.
+    pub(crate) async fn send(
+        &self,
+        retry: Retry,
+        request: &HttpRequest,
+    ) -> Result<HttpResponse, PostError> {
+        send_checked(
+            self.transport.as_ref(),
+            &self.service.client_config,
+            retry,
+            request,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::Post;
+    use crate::post_status::PostStatus;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_client_sends_requests_with_configured_user_agent() {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let api_url = format!(
+            "http://127.0.0.1:{}/micropub",
+            listener.local_addr().unwrap().port()
+        );
+        // Both requests arrive on the same connection, which the client keeps open.
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut requests = Vec::new();
+            for url in ["https://example.com/1", "https://example.com/2"] {
+                let mut request = [0u8; 4096];
+                let length = stream.read(&mut request).await.unwrap();
+                requests.push(String::from_utf8_lossy(&request[..length]).to_lowercase());
+                let response = format!(
+                    "HTTP/1.1 201 Created\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+                    url
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let mut service = MicropubService::new(api_url, "token".to_string());
+        service.client_config.user_agent = "test-agent/1.0".to_string();
+        let client = MicropubClient::new(service).unwrap();
+        for (body, url) in [
+            ("First", "https://example.com/1"),
+            ("Second", "https://example.com/2"),
+        ] {
+            let post = Post::from_body(body.to_string(), PostStatus::Published);
+            assert_eq!(client.create(post).await.unwrap().url, url);
+        }
+
+        let requests = server.await.unwrap();
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.contains("user-agent: test-agent/1.0\r\n"))
+        );
+    }
+}
@@ -1,10 +1,22 @@
 use serde::{Deserialize, Serialize};
 use std::io;
 
-#[derive(Serialize, Deserialize)]
+use crate::http::ClientConfig;
+use crate::request_format::RequestFormat;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MicropubService {
     pub api_url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub auth_token: String,
+    #[serde(default)]
+    pub request_format: RequestFormat,
+    /// The IndieAuth token endpoint that issued the token, used to verify it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_endpoint: Option<String>,
+    /// Timeouts and retries of the requests sent to the service.
+    #[serde(skip)]
+    pub client_config: ClientConfig,
 }
 
 impl MicropubService {
@@ -12,9 +24,30 @@ impl MicropubService {
         MicropubService {
             api_url,
             auth_token,
+            request_format: RequestFormat::default(),
+            token_endpoint: None,
+            client_config: ClientConfig::default(),
         }
     }
 
+    /// Use the given syntax for requests sent to this service.
+    pub fn with_request_format(mut self, request_format: RequestFormat) -> Self {
+        self.request_format = request_format;
+        self
+    }
+
+    /// Verify the token against the given IndieAuth token endpoint.
+    pub fn with_token_endpoint(mut self, token_endpoint: Option<String>) -> Self {
+        self.token_endpoint = token_endpoint;
+        self
+    }
+
+    /// Send requests to this service with the given timeouts and retries.
+    pub fn with_client_config(mut self, client_config: ClientConfig) -> Self {
+        self.client_config = client_config;
+        self
+    }
+
     pub fn from_args(api_url: String, auth_token: String) -> io::Result<Self> {
         if api_url.is_empty() || auth_token.is_empty() {
             return Err(io::Error::new(
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::post_error::PostError;
+use crate::transport::{HttpRequest, HttpResponse, Transport, TransportFuture};
+
+/// A transport that answers requests with prepared responses and records the requests it
+/// receives, so that code sending Micropub requests can be tested without a server.
+///
+/// Responses and failures are returned in the order they were added, one per request. Clones of
+/// a mock transport share their responses and recorded requests, so a clone can be given to a
+/// [`crate::MicropubClient`] and the original inspected afterwards.
+///
+/// ```
+/// use mplib::{HttpResponse, MicropubClient, MicropubService, MockTransport, Post, PostStatus};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let transport = MockTransport::new();
+/// transport.respond_with(HttpResponse::new(201, "").with_header("Location", "https://example.com/1"));
+///
+/// let service = MicropubService::new("https://example.com/micropub".to_string(), "token".to_string());
+/// let client = MicropubClient::with_transport(service, transport.clone());
+/// let post = Post::from_body("Hello".to_string(), PostStatus::Published);
+///
+/// assert_eq!(client.create(post).await.unwrap().url, "https://example.com/1");
+/// assert_eq!(transport.requests().len(), 1);
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    results: VecDeque<Result<HttpResponse, PostError>>,
+    requests: Vec<HttpRequest>,
+}
+
+impl MockTransport {
+    /// Create a mock transport without any prepared responses.
+    pub fn new() -> Self {
+        MockTransport::default()
+    }
+
+    /// Answer the next request with the given response. A response without a URL gets the URL
+    /// of the request.
+    pub fn respond_with(&self, response: HttpResponse) {
+        self.state().results.push_back(Ok(response));
+    }
+
+    /// Fail the next request with the given error, such as [`PostError::ConnectFailed`] for a
+    /// server that cannot be reached.
+    pub fn fail_with(&self, error: PostError) {
+        self.state().results.push_back(Err(error));
+    }
+
+    /// Get the requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // A test that panicked while holding the lock cannot leave the state half-updated.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Transport for MockTransport {
+    /// Record the request and return the next prepared result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no response or failure is left, since the code under test sent more requests
+    /// than expected.
+    fn send<'a>(&'a self, request: &'a HttpRequest) -> TransportFuture<'a> {
+        let mut state = self.state();
+        state.requests.push(request.clone());
+        let result = state.results.pop_front().unwrap_or_else(|| {
+            panic!(
+                "MockTransport received an unexpected request to {}",
+                request.url
+            )
+        });
+        let result = result.map(|mut response| {
+            if response.url.is_empty() {
+                response.url = request.url.clone();
+            }
+            response
+        });
+        Box::pin(std::future::ready(result))
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::properties::PropertyValue;
+
+/// Represents a photo attached to a post.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Photo {
+    /// The URL of the photo, typically returned by the media endpoint.
+    pub url: String,
+    /// Alternative text describing the photo.
+    pub alt: Option<String>,
+}
+
+impl Photo {
+    pub fn new(url: String, alt: Option<String>) -> Self {
+        Photo { url, alt }
+    }
+
+    /// Convert the photo into the value of a Micropub `photo` property.
+    pub fn to_property_value(&self) -> PropertyValue {
+        match &self.alt {
+            Some(alt) => PropertyValue::WithAlt {
+                value: self.url.clone(),
+                alt: alt.clone(),
+            },
+            None => PropertyValue::Text(self.url.clone()),
+        }
+    }
+}
@@ -1,40 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::photo::Photo;
 use crate::post_status::PostStatus;
+use crate::properties::{Properties, PropertyValue};
 
 /// Represents a post to be published.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Post {
     pub body: String,
     pub title: Option<String>,
     pub status: PostStatus,
+    /// Whether the body is HTML rather than plain text or Markdown.
+    pub html: bool,
+    /// Categories (tags) of the post.
+    pub categories: Vec<String>,
+    /// UIDs of the targets the post should be syndicated to (`mp-syndicate-to`).
+    pub syndicate_to: Vec<String>,
+    /// UID of the blog the post should be published to (`mp-destination`), for services that
+    /// host several blogs per account.
+    pub destination: Option<String>,
+    /// Photos attached to the post.
+    pub photos: Vec<Photo>,
+    /// URLs of videos attached to the post.
+    pub videos: Vec<String>,
+    /// URLs of audio files attached to the post.
+    pub audio: Vec<String>,
+    /// Publication date of the post, as an ISO 8601 date or date-time.
+    pub published: Option<String>,
+    /// Slug the server should use in the URL of the post (`mp-slug`).
+    pub slug: Option<String>,
+    /// URL of the post this post is a reply to.
+    pub in_reply_to: Option<String>,
 }
 
 impl Post {
-    /// Create a new Post with the given body and status, without a title.
-    pub fn from_body(body: String, status: PostStatus) -> Self {
+    /// Create a new Post with the given body, optional title, and status, and no attachments.
+    fn new(body: String, title: Option<String>, status: PostStatus) -> Self {
         Post {
             body,
-            title: None,
+            title,
             status,
+            html: false,
+            categories: Vec::new(),
+            syndicate_to: Vec::new(),
+            destination: None,
+            photos: Vec::new(),
+            videos: Vec::new(),
+            audio: Vec::new(),
+            published: None,
+            slug: None,
+            in_reply_to: None,
         }
     }
 
+    /// Create a new Post with the given body and status, without a title.
+    pub fn from_body(body: String, status: PostStatus) -> Self {
+        Post::new(body, None, status)
+    }
+
     /// Create a new Post with the given body, title, and status.
     pub fn from_body_and_title(body: String, title: String, status: PostStatus) -> Self {
-        Post {
-            body,
-            title: Some(title),
-            status,
-        }
+        Post::new(body, Some(title), status)
     }
 
     /// Create a new Post by extracting the title from the body if it starts with a markdown
     /// level 1 header (i.e., a line starting with '# ').
     pub fn from_body_with_title_extraction(body: String, status: PostStatus) -> Self {
         let (body, title) = Self::separate_title_from_body(body);
-        Post {
-            body,
-            title,
-            status,
-        }
+        Post::new(body, title, status)
     }
 
     /// Separate the title from the body if the body starts with a markdown level 1 header.
@@ -61,9 +94,63 @@ impl Post {
         }
     }
 
-    /// Check if the post body is empty or consists only of whitespace.
+    /// Check if the post body is empty or consists only of whitespace, and the post has no
+    /// attachments.
     pub fn is_empty(&self) -> bool {
-        self.body.trim().is_empty()
+        self.body.trim().is_empty() && !self.has_attachments()
+    }
+
+    /// Check if the post has photo, video, or audio attachments.
+    pub fn has_attachments(&self) -> bool {
+        !self.photos.is_empty() || !self.videos.is_empty() || !self.audio.is_empty()
+    }
+
+    /// Convert the post into the Micropub properties of an h-entry.
+    pub fn to_properties(&self) -> Properties {
+        let mut properties = Properties::new();
+
+        // Media-only posts have no content.
+        if !self.body.trim().is_empty() || !self.has_attachments() {
+            let content = if self.html {
+                PropertyValue::Html(self.body.clone())
+            } else {
+                PropertyValue::Text(self.body.clone())
+            };
+            properties.push("content", content);
+        }
+        properties.push_text("post-status", self.status.as_str());
+        if let Some(title) = &self.title {
+            properties.push_text("name", title);
+        }
+        for category in &self.categories {
+            properties.push_list_text("category", category);
+        }
+        for target in &self.syndicate_to {
+            properties.push_list_text("mp-syndicate-to", target);
+        }
+        if let Some(published) = &self.published {
+            properties.push_text("published", published);
+        }
+        if let Some(slug) = &self.slug {
+            properties.push_text("mp-slug", slug);
+        }
+        if let Some(in_reply_to) = &self.in_reply_to {
+            properties.push_text("in-reply-to", in_reply_to);
+        }
+        if let Some(destination) = &self.destination {
+            properties.push_text("mp-destination", destination);
+        }
+        for photo in &self.photos {
+            properties.push("photo", photo.to_property_value());
+        }
+        for video in &self.videos {
+            properties.push_text("video", video);
+        }
+        for audio in &self.audio {
+            properties.push_text("audio", audio);
+        }
+
+        properties
     }
 }
 
@@ -125,6 +212,157 @@ mod tests {
         assert_eq!(post.body, "Just body");
     }
 
+    #[test]
+    fn test_to_properties_includes_title_and_status() {
+        let post =
+            Post::from_body_and_title("Body".to_string(), "Title".to_string(), PostStatus::Draft);
+        let properties = post.to_properties();
+        assert_eq!(
+            properties.get("content"),
+            Some(&[PropertyValue::Text("Body".to_string())][..])
+        );
+        assert_eq!(
+            properties.get("name"),
+            Some(&[PropertyValue::Text("Title".to_string())][..])
+        );
+        assert_eq!(
+            properties.get("post-status"),
+            Some(&[PropertyValue::Text("draft".to_string())][..])
+        );
+    }
+
+    #[test]
+    fn test_to_properties_html_content() {
+        let mut post = Post::from_body("<p>Body</p>".to_string(), PostStatus::Published);
+        post.html = true;
+        assert_eq!(
+            post.to_properties().get("content"),
+            Some(&[PropertyValue::Html("<p>Body</p>".to_string())][..])
+        );
+        assert_eq!(post.to_properties().get("name"), None);
+    }
+
+    #[test]
+    fn test_is_empty_with_attachment_only() {
+        let mut post = Post::from_body(String::new(), PostStatus::Published);
+        post.photos
+            .push(Photo::new("https://example.com/a.jpg".to_string(), None));
+        assert!(!post.is_empty());
+        assert_eq!(post.to_properties().get("content"), None);
+    }
+
+    #[test]
+    fn test_to_properties_categories() {
+        let mut post = Post::from_body("Body".to_string(), PostStatus::Published);
+        post.categories = vec!["rust".to_string(), "indieweb".to_string()];
+        assert_eq!(
+            post.to_properties().get("category"),
+            Some(
+                &[
+                    PropertyValue::Text("rust".to_string()),
+                    PropertyValue::Text("indieweb".to_string())
+                ][..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_properties_syndication_targets() {
+        let mut post = Post::from_body("Body".to_string(), PostStatus::Published);
+        post.syndicate_to = vec!["https://mastodon.example/@me".to_string()];
+        assert_eq!(
+            post.to_properties().to_form("entry").last(),
+            Some(&(
+                "mp-syndicate-to[]".to_string(),
+                "https://mastodon.example/@me".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_to_properties_destination() {
+        let mut post = Post::from_body("Body".to_string(), PostStatus::Published);
+        assert_eq!(post.to_properties().get("mp-destination"), None);
+
+        post.destination = Some("https://blog.example.com/".to_string());
+        assert_eq!(
+            post.to_properties().get("mp-destination"),
+            Some(&[PropertyValue::Text("https://blog.example.com/".to_string())][..])
+        );
+    }
+
+    #[test]
+    fn test_to_properties_published_slug_and_reply() {
+        let mut post = Post::from_body("Body".to_string(), PostStatus::Published);
+        post.published = Some("2024-05-01T09:30:00+02:00".to_string());
+        post.slug = Some("hello-world".to_string());
+        post.in_reply_to = Some("https://example.com/other-post".to_string());
+
+        let properties = post.to_properties();
+        assert_eq!(
+            properties.get("published"),
+            Some(&[PropertyValue::Text("2024-05-01T09:30:00+02:00".to_string())][..])
+        );
+        assert_eq!(
+            properties.get("mp-slug"),
+            Some(&[PropertyValue::Text("hello-world".to_string())][..])
+        );
+        assert_eq!(
+            properties.get("in-reply-to"),
+            Some(
+                &[PropertyValue::Text(
+                    "https://example.com/other-post".to_string()
+                )][..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let mut post =
+            Post::from_body_and_title("Body".to_string(), "Title".to_string(), PostStatus::Draft);
+        post.categories = vec!["rust".to_string()];
+        post.photos.push(Photo::new(
+            "https://example.com/a.jpg".to_string(),
+            Some("A cat".to_string()),
+        ));
+
+        let json = serde_json::to_string(&post).unwrap();
+        assert!(json.contains(r#""status":"draft""#));
+        let deserialized: Post = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.to_properties(), post.to_properties());
+    }
+
+    #[test]
+    fn test_to_properties_attachments() {
+        let mut post = Post::from_body("Body".to_string(), PostStatus::Published);
+        post.photos.push(Photo::new(
+            "https://example.com/a.jpg".to_string(),
+            Some("A cat".to_string()),
+        ));
+        post.videos.push("https://example.com/v.mp4".to_string());
+        post.audio.push("https://example.com/a.mp3".to_string());
+
+        let properties = post.to_properties();
+        assert_eq!(
+            properties.get("photo"),
+            Some(
+                &[PropertyValue::WithAlt {
+                    value: "https://example.com/a.jpg".to_string(),
+                    alt: "A cat".to_string()
+                }][..]
+            )
+        );
+        assert_eq!(
+            properties.get("video"),
+            Some(&[PropertyValue::Text("https://example.com/v.mp4".to_string())][..])
+        );
+        assert_eq!(
+            properties.get("audio"),
+            Some(&[PropertyValue::Text("https://example.com/a.mp3".to_string())][..])
+        );
+    }
+
     #[test]
     fn test_separate_title_from_body_heading_1_in_first_line() {
         let body = "# My Title\nThis is the body".to_string();
@@ -0,0 +1,82 @@
+use thiserror::Error;
+
+/// Represents errors that can occur while sending requests to a Micropub endpoint.
+#[derive(Error, Debug)]
+pub enum PostError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("Could not connect: {0}")]
+    ConnectFailed(String),
+    #[error("Connection lost: {0}")]
+    ConnectionLost(String),
+    #[error("API error: {error} - {description}")]
+    Api { error: String, description: String },
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Insufficient scope: {0}")]
+    InsufficientScope(String),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("HTTP error {status}: {body}")]
+    Http { status: u16, body: String },
+    #[error("JSON parsing error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("The server did not return the URL of the created resource")]
+    MissingLocation,
+    #[error("The site does not advertise a {0}")]
+    MissingEndpoint(String),
+    #[error("Authorization failed: {0}")]
+    Authorization(String),
+}
+
+impl PostError {
+    /// Check if the error is likely to go away when the request is sent again later: the server
+    /// could not be reached, or it failed with a server error.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            PostError::Network(_) | PostError::ConnectFailed(_) | PostError::ConnectionLost(_) => {
+                true
+            }
+            PostError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Check if the request may have been processed by the server even though it failed, so
+    /// that sending it again could create a duplicate: the connection was lost or timed out
+    /// after the request was sent, a gateway in front of the server reported an error (502, 504,
+    /// or the 52x errors of CDNs) after passing the request on, or the response of the server
+    /// was not understood.
+    pub fn is_delivery_uncertain(&self) -> bool {
+        match self {
+            PostError::Network(e) => !e.is_connect() && !e.is_builder(),
+            PostError::ConnectionLost(_) | PostError::MissingLocation => true,
+            PostError::Http { status, .. } => matches!(status, 502 | 504 | 520..=599),
+            _ => false,
+        }
+    }
+
+    /// Check if no connection could be made, so the request was certainly not sent.
+    pub(crate) fn is_connect_failure(&self) -> bool {
+        match self {
+            PostError::Network(e) => e.is_connect(),
+            PostError::ConnectFailed(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Check if the request could not be delivered, or its response could not be received.
+    pub fn is_transport_failure(&self) -> bool {
+        match self {
+            PostError::Network(e) => !e.is_builder(),
+            PostError::ConnectFailed(_) | PostError::ConnectionLost(_) => true,
+            _ => false,
+        }
+    }
+}
@@ -1,22 +1,35 @@
+use crate::creation_status::CreationStatus;
 use crate::post_status::PostStatus;
 
 /// Represents a micropub endpoint's response to a request to publish a post.
 pub struct PostResult {
     pub url: String,
-    pub preview: String,
-    pub edit: String,
+    pub preview: Option<String>,
+    pub edit: Option<String>,
     pub post_status: PostStatus,
+    pub creation_status: CreationStatus,
 }
 
 impl PostResult {
     /// Format the PostResult as a human-readable string.
     pub fn as_string(&self) -> String {
-        format!(
-            "{} successfully.\n\nURL:     {}\nPreview: {}\nEdit:    {}",
-            self.post_status.action_description(),
-            &self.url,
-            &self.preview,
-            &self.edit
-        )
+        let headline = match self.creation_status {
+            CreationStatus::Created => {
+                format!("{} successfully.", self.post_status.action_description())
+            }
+            CreationStatus::Accepted => format!(
+                "{} successfully. The server accepted it and may still be processing it.",
+                self.post_status.action_description()
+            ),
+        };
+
+        let mut output = format!("{}\n\nURL:     {}", headline, &self.url);
+        if let Some(preview) = &self.preview {
+            output.push_str(&format!("\nPreview: {}", preview));
+        }
+        if let Some(edit) = &self.edit {
+            output.push_str(&format!("\nEdit:    {}", edit));
+        }
+        output
     }
 }
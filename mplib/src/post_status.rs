@@ -1,5 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 /// Represents the status of a post, either published or draft.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PostStatus {
     Published,
     Draft,
@@ -0,0 +1,263 @@
+use serde_json::{Map, Value, json};
+
+/// Represents a single value of a Micropub property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyValue {
+    /// A plain text value.
+    Text(String),
+    /// An HTML value, sent as `{"html": ...}` in JSON syntax.
+    Html(String),
+    /// A URL with alternative text, sent as `{"value": ..., "alt": ...}` in JSON syntax.
+    WithAlt { value: String, alt: String },
+}
+
+/// Represents an ordered collection of Micropub properties that can be encoded in either
+/// form-encoded or JSON syntax.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    entries: Vec<Property>,
+}
+
+/// Represents a named property and its values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Property {
+    name: String,
+    values: Vec<PropertyValue>,
+    /// Whether the property is a list, even when it holds a single value.
+    is_list: bool,
+}
+
+impl Properties {
+    /// Create an empty collection of properties.
+    pub fn new() -> Self {
+        Properties::default()
+    }
+
+    /// Append a value to the named property, creating the property if it does not exist yet.
+    pub fn push(&mut self, name: &str, value: PropertyValue) {
+        self.entry(name).values.push(value);
+    }
+
+    /// Append a plain text value to the named list property (e.g., `category`). List properties
+    /// are always sent with the `[]` suffix in form encoding, even when they hold one value.
+    pub fn push_list_text(&mut self, name: &str, value: &str) {
+        let entry = self.entry(name);
+        entry.is_list = true;
+        entry.values.push(PropertyValue::Text(value.to_string()));
+    }
+
+    /// Get the named property, creating it if it does not exist yet.
+    fn entry(&mut self, name: &str) -> &mut Property {
+        let index = match self.entries.iter().position(|p| p.name == name) {
+            Some(index) => index,
+            None => {
+                self.entries.push(Property {
+                    name: name.to_string(),
+                    values: Vec::new(),
+                    is_list: false,
+                });
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[index]
+    }
+
+    /// Append a plain text value to the named property.
+    pub fn push_text(&mut self, name: &str, value: &str) {
+        self.push(name, PropertyValue::Text(value.to_string()));
+    }
+
+    /// Check if the collection contains no properties.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the values of the named property.
+    pub fn get(&self, name: &str) -> Option<&[PropertyValue]> {
+        self.entries
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.values.as_slice())
+    }
+
+    /// Encode the properties as form parameters for an object of the given type (e.g., `entry`).
+    ///
+    /// Multi-valued properties are sent with the `[]` suffix. Form encoding cannot express HTML
+    /// values, so only their text is sent. The alternative texts of a property are sent in a
+    /// parallel `mp-<name>-alt` parameter, with an empty text for values that have none.
+    pub fn to_form(&self, object_type: &str) -> Vec<(String, String)> {
+        let mut params = vec![("h".to_string(), object_type.to_string())];
+
+        for Property {
+            name,
+            values,
+            is_list,
+        } in &self.entries
+        {
+            let key = if *is_list || values.len() > 1 {
+                format!("{}[]", name)
+            } else {
+                name.clone()
+            };
+            let mut alts = Vec::new();
+            for value in values {
+                let (text, alt) = match value {
+                    PropertyValue::Text(text) | PropertyValue::Html(text) => (text, None),
+                    PropertyValue::WithAlt { value, alt } => (value, Some(alt)),
+                };
+                params.push((key.clone(), text.clone()));
+                alts.push(alt);
+            }
+
+            if alts.iter().any(Option::is_some) {
+                let alt_key = format!("mp-{}-alt{}", name, &key[name.len()..]);
+                for alt in alts {
+                    params.push((alt_key.clone(), alt.cloned().unwrap_or_default()));
+                }
+            }
+        }
+
+        params
+    }
+
+    /// Encode the properties as a JSON object for an object of the given type (e.g., `entry`).
+    pub fn to_json(&self, object_type: &str) -> Value {
+        json!({
+            "type": [format!("h-{}", object_type)],
+            "properties": self.to_json_map(),
+        })
+    }
+
+    /// Encode the properties as a JSON map of property names to arrays of values.
+    pub fn to_json_map(&self) -> Map<String, Value> {
+        self.entries
+            .iter()
+            .map(|Property { name, values, .. }| {
+                let values = values
+                    .iter()
+                    .map(|value| match value {
+                        PropertyValue::Text(text) => Value::String(text.clone()),
+                        PropertyValue::Html(html) => json!({ "html": html }),
+                        PropertyValue::WithAlt { value, alt } => {
+                            json!({ "value": value, "alt": alt })
+                        }
+                    })
+                    .collect();
+                (name.clone(), Value::Array(values))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_properties() -> Properties {
+        let mut properties = Properties::new();
+        properties.push_text("content", "Hello");
+        properties.push_text("category", "one");
+        properties.push_text("category", "two");
+        properties
+    }
+
+    #[test]
+    fn test_to_form_single_and_multi_valued_properties() {
+        let params = sample_properties().to_form("entry");
+        let expected = [
+            ("h", "entry"),
+            ("content", "Hello"),
+            ("category[]", "one"),
+            ("category[]", "two"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(params, expected);
+    }
+
+    #[test]
+    fn test_to_form_list_property_with_single_value() {
+        let mut properties = Properties::new();
+        properties.push_list_text("category", "one");
+        assert_eq!(
+            properties.to_form("entry")[1],
+            ("category[]".to_string(), "one".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_json_wraps_values_in_arrays() {
+        let body = sample_properties().to_json("entry");
+        assert_eq!(
+            body,
+            json!({
+                "type": ["h-entry"],
+                "properties": {
+                    "content": ["Hello"],
+                    "category": ["one", "two"],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_alt_value_encoding() {
+        let mut properties = Properties::new();
+        properties.push(
+            "photo",
+            PropertyValue::WithAlt {
+                value: "https://example.com/a.jpg".to_string(),
+                alt: "A cat".to_string(),
+            },
+        );
+
+        assert_eq!(
+            properties.to_json("entry")["properties"]["photo"],
+            json!([{ "value": "https://example.com/a.jpg", "alt": "A cat" }])
+        );
+        assert_eq!(
+            properties.to_form("entry")[1..],
+            [
+                ("photo".to_string(), "https://example.com/a.jpg".to_string()),
+                ("mp-photo-alt".to_string(), "A cat".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_form_alt_texts_parallel_to_values() {
+        let mut properties = Properties::new();
+        properties.push_text("photo", "https://example.com/a.jpg");
+        properties.push(
+            "photo",
+            PropertyValue::WithAlt {
+                value: "https://example.com/b.jpg".to_string(),
+                alt: "A dog".to_string(),
+            },
+        );
+
+        let expected = [
+            ("h", "entry"),
+            ("photo[]", "https://example.com/a.jpg"),
+            ("photo[]", "https://example.com/b.jpg"),
+            ("mp-photo-alt[]", ""),
+            ("mp-photo-alt[]", "A dog"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(properties.to_form("entry"), expected);
+    }
+
+    #[test]
+    fn test_html_value_encoding() {
+        let mut properties = Properties::new();
+        properties.push("content", PropertyValue::Html("<p>Hi</p>".to_string()));
+
+        assert_eq!(
+            properties.to_json("entry")["properties"]["content"],
+            json!([{ "html": "<p>Hi</p>" }])
+        );
+        assert_eq!(
+            properties.to_form("entry")[1],
+            ("content".to_string(), "<p>Hi</p>".to_string())
+        );
+    }
+}
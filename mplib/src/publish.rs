@@ -1,108 +1,380 @@
-use std::time::Duration;
-
-use reqwest::ClientBuilder;
 use serde::Deserialize;
 use serde_json::from_str;
-use thiserror::Error;
 
+use crate::creation_status::CreationStatus;
+use crate::http::{Retry, location_header};
+use crate::micropub_client::MicropubClient;
 pub use crate::micropub_service::MicropubService;
 use crate::post::Post;
+pub use crate::post_error::PostError;
+use crate::post_status::PostStatus;
+use crate::transport::HttpRequest;
 
 pub use crate::post_result::PostResult;
 
-const DEFAULT_TIMEOUT_SECS: u64 = 5;
+/// The header that identifies a request, so that servers supporting it can recognize a request
+/// that is sent again and not create the post twice.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
 
 /// Publish a Post via a Micropub service.
+///
+/// This creates a new client for the request. To publish several posts, use a
+/// [`MicropubClient`] instead.
 pub async fn publish_post(post: Post, service: &MicropubService) -> Result<PostResult, PostError> {
-    if post.is_empty() {
-        return Err(PostError::InvalidInput(
-            "Post content cannot be empty".to_string(),
-        ));
+    MicropubClient::new(service.clone())?.create(post).await
+}
+
+/// Publish a Post via a Micropub service, identifying the request with an idempotency key.
+///
+/// Sending the same post again with the same key after a request whose response was lost lets
+/// servers that support the `Idempotency-Key` header return the original post instead of
+/// creating a duplicate. Other servers ignore the key, so the post is only retried automatically
+/// after it may have been sent if the service's client configuration says the server honors the
+/// key (see [`crate::ClientConfig::honors_idempotency_key`]).
+pub async fn publish_post_with_idempotency_key(
+    post: Post,
+    service: &MicropubService,
+    idempotency_key: &str,
+) -> Result<PostResult, PostError> {
+    MicropubClient::new(service.clone())?
+        .create_with_idempotency_key(post, idempotency_key)
+        .await
+}
+
+impl MicropubClient {
+    /// Publish a Post.
+    pub async fn create(&self, post: Post) -> Result<PostResult, PostError> {
+        self.publish(post, None).await
     }
 
-    let client = build_client()?;
-    let post_status = post.status.clone();
-    let response = send_post_request(&client, post, service).await?;
+    /// Publish a Post, identifying the request with an idempotency key (see
+    /// [`publish_post_with_idempotency_key`]).
+    pub async fn create_with_idempotency_key(
+        &self,
+        post: Post,
+        idempotency_key: &str,
+    ) -> Result<PostResult, PostError> {
+        self.publish(post, Some(idempotency_key)).await
+    }
 
-    let status = response.status();
-    let text: String = response.text().await?;
+    async fn publish(
+        &self,
+        post: Post,
+        idempotency_key: Option<&str>,
+    ) -> Result<PostResult, PostError> {
+        if post.is_empty() {
+            return Err(PostError::InvalidInput(
+                "Post content cannot be empty".to_string(),
+            ));
+        }
 
-    if !status.is_success() {
-        let api_error: ApiPostError = from_str(&text)?;
-        return Err(PostError::Api {
-            error: api_error.error,
-            description: api_error.error_description,
-        });
+        let service = self.service();
+
+        // Encode the post in the syntax the service is configured to use.
+        let properties = post.to_properties();
+        let mut request = HttpRequest::post(&service.api_url)
+            .with_bearer_auth(&service.auth_token)
+            .with_body(service.request_format.encode_entry(&properties));
+        if let Some(idempotency_key) = idempotency_key {
+            request = request.with_header(IDEMPOTENCY_KEY_HEADER, idempotency_key);
+        }
+
+        // Unless the server recognizes the key, sending the post twice could publish it twice.
+        let retry = if idempotency_key.is_some() && service.client_config.honors_idempotency_key {
+            Retry::Idempotent
+        } else {
+            Retry::UnlessSent
+        };
+        let response = self.send(retry, &request).await?;
+
+        post_result_from_response(
+            response.status,
+            location_header(&response),
+            &response.body,
+            post.status,
+        )
     }
+}
+
+/// Build a PostResult from a successful response.
+///
+/// Spec-compliant servers return the post's URL in the `Location` header, usually with an empty
+/// body. Micro.blog additionally returns `url`, `preview`, and `edit` in a JSON body, so the body
+/// is parsed leniently and only used to fill in what the headers do not provide.
+fn post_result_from_response(
+    status: u16,
+    location: Option<String>,
+    text: &str,
+    post_status: PostStatus,
+) -> Result<PostResult, PostError> {
+    let api_response: ApiPostResponse = from_str(text).unwrap_or_default();
+
+    let url = location
+        .or(api_response.url)
+        .ok_or(PostError::MissingLocation)?;
+
+    let creation_status = if status == 202 {
+        CreationStatus::Accepted
+    } else {
+        CreationStatus::Created
+    };
 
-    let api_response: ApiPostResponse = from_str(&text)?;
     Ok(PostResult {
-        url: api_response.url,
+        url,
         preview: api_response.preview,
         edit: api_response.edit,
         post_status,
+        creation_status,
     })
 }
 
-/// Build an HTTP client with a default timeout.
-fn build_client() -> Result<reqwest::Client, PostError> {
-    let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
-    ClientBuilder::new()
-        .timeout(timeout)
-        .build()
-        .map_err(PostError::Network)
+/// Represents the optional JSON body some Micropub APIs (e.g., Micro.blog) return upon
+/// successful post creation.
+#[derive(Deserialize, Default)]
+struct ApiPostResponse {
+    url: Option<String>,
+    preview: Option<String>,
+    edit: Option<String>,
 }
 
-/// Send a POST request to the Micropub endpoint with the given post data.
-async fn send_post_request(
-    client: &reqwest::Client,
-    post: Post,
-    microblog_service: &MicropubService,
-) -> Result<reqwest::Response, PostError> {
-    // Prepare form parameters. If post has a title, include it. Otherwise, omit it.
-    let params = [
-        ("h", "entry"),
-        ("content", post.body.as_str()),
-        ("post-status", post.status.as_str()),
-    ]
-    .into_iter()
-    .chain(post.title.as_ref().map(|t| ("name", t.as_str())))
-    .collect::<Vec<_>>();
-
-    // Send the POST request.
-    client
-        .post(&microblog_service.api_url)
-        .form(&params)
-        .bearer_auth(&microblog_service.auth_token)
-        .send()
-        .await
-        .map_err(PostError::Network)
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_transport::MockTransport;
+    use crate::transport::{HttpBody, HttpMethod, HttpResponse};
 
-/// Represents errors that can occur while publishing a post.
-#[derive(Error, Debug)]
-pub enum PostError {
-    #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
-    #[error("API error: {error} - {description}")]
-    Api { error: String, description: String },
-    #[error("JSON parsing error: {0}")]
-    Json(#[from] serde_json::Error),
-    #[error("Invalid input: {0}")]
-    InvalidInput(String),
-}
+    fn mock_client() -> (MicropubClient, MockTransport) {
+        let transport = MockTransport::new();
+        let mut service = MicropubService::new(
+            "https://example.com/micropub".to_string(),
+            "token".to_string(),
+        );
+        service.client_config.initial_backoff = std::time::Duration::from_millis(1);
+        let client = MicropubClient::with_transport(service, transport.clone());
+        (client, transport)
+    }
 
-/// Represents the expected response from the Micropub API upon successful post creation.
-#[derive(Deserialize)]
-struct ApiPostResponse {
-    url: String,
-    preview: String,
-    edit: String,
-}
+    fn hello_post() -> Post {
+        Post::from_body("Hello".to_string(), PostStatus::Published)
+    }
+
+    #[tokio::test]
+    async fn test_create_sends_post_and_returns_its_url() {
+        let (client, transport) = mock_client();
+        transport.respond_with(
+            HttpResponse::new(201, "").with_header("Location", "https://example.com/1"),
+        );
+
+        let result = client.create(hello_post()).await.unwrap();
+        assert_eq!(result.url, "https://example.com/1");
+        assert_eq!(result.creation_status, CreationStatus::Created);
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, HttpMethod::Post);
+        assert_eq!(requests[0].url, "https://example.com/micropub");
+        assert_eq!(requests[0].bearer_token.as_deref(), Some("token"));
+        assert_eq!(requests[0].header(IDEMPOTENCY_KEY_HEADER), None);
+        let HttpBody::Form(params) = &requests[0].body else {
+            panic!("expected a form-encoded body");
+        };
+        assert!(params.contains(&("h".to_string(), "entry".to_string())));
+        assert!(params.contains(&("content".to_string(), "Hello".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_create_reports_api_error() {
+        let (client, transport) = mock_client();
+        transport.respond_with(HttpResponse::new(
+            401,
+            r#"{"error":"unauthorized","error_description":"Bad token"}"#,
+        ));
+
+        let result = client.create(hello_post()).await;
+        assert!(
+            matches!(result, Err(PostError::Unauthorized(description)) if description == "Bad token")
+        );
+    }
 
-/// Represents the expected error response from the Micropub API upon a failed post creation.
-#[derive(Deserialize)]
-struct ApiPostError {
-    error: String,
-    error_description: String,
+    #[tokio::test]
+    async fn test_create_ignores_malformed_json_body() {
+        let (client, transport) = mock_client();
+        transport.respond_with(
+            HttpResponse::new(202, "{not json").with_header("Location", "https://example.com/1"),
+        );
+        transport.respond_with(HttpResponse::new(202, "{not json"));
+
+        let result = client.create(hello_post()).await.unwrap();
+        assert_eq!(result.url, "https://example.com/1");
+        assert_eq!(result.creation_status, CreationStatus::Accepted);
+
+        // Without a Location header, the URL can only come from the body.
+        let result = client.create(hello_post()).await;
+        assert!(matches!(result, Err(PostError::MissingLocation)));
+    }
+
+    #[tokio::test]
+    async fn test_create_network_failure_is_not_retried_without_idempotency_key() {
+        let (client, transport) = mock_client();
+        transport.fail_with(PostError::ConnectionLost("reset".to_string()));
+
+        let Err(error) = client.create(hello_post()).await else {
+            panic!("expected the post to fail");
+        };
+        assert!(error.is_transient());
+        assert!(error.is_delivery_uncertain());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_create_with_idempotency_key_is_sent_once_after_network_failure() {
+        let (client, transport) = mock_client();
+        transport.fail_with(PostError::ConnectionLost("reset".to_string()));
+
+        let Err(error) = client
+            .create_with_idempotency_key(hello_post(), "key-1")
+            .await
+        else {
+            panic!("expected the post to fail");
+        };
+        assert!(error.is_delivery_uncertain());
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header(IDEMPOTENCY_KEY_HEADER), Some("key-1"));
+    }
+
+    #[tokio::test]
+    async fn test_create_gateway_error_is_uncertain_and_not_retried() {
+        let (client, transport) = mock_client();
+        transport.respond_with(HttpResponse::new(504, "Gateway Timeout"));
+        transport.respond_with(HttpResponse::new(503, "Service Unavailable"));
+
+        let Err(error) = client
+            .create_with_idempotency_key(hello_post(), "key-1")
+            .await
+        else {
+            panic!("expected the post to fail");
+        };
+        assert!(error.is_transient());
+        assert!(error.is_delivery_uncertain());
+        assert_eq!(transport.requests().len(), 1);
+
+        // The server itself reports that it is unavailable, so the post was not created.
+        let Err(error) = client.create(hello_post()).await else {
+            panic!("expected the post to fail");
+        };
+        assert!(error.is_transient());
+        assert!(!error.is_delivery_uncertain());
+    }
+
+    #[tokio::test]
+    async fn test_create_with_idempotency_key_is_retried_if_the_server_honors_it() {
+        let transport = MockTransport::new();
+        let mut service = MicropubService::new(
+            "https://example.com/micropub".to_string(),
+            "token".to_string(),
+        );
+        service.client_config.initial_backoff = std::time::Duration::from_millis(1);
+        service.client_config.honors_idempotency_key = true;
+        let client = MicropubClient::with_transport(service, transport.clone());
+        transport.fail_with(PostError::ConnectionLost("reset".to_string()));
+        transport.respond_with(
+            HttpResponse::new(201, "").with_header("Location", "https://example.com/1"),
+        );
+
+        let result = client
+            .create_with_idempotency_key(hello_post(), "key-1")
+            .await
+            .unwrap();
+        assert_eq!(result.url, "https://example.com/1");
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_post_result_from_location_header_with_empty_body() {
+        let result = post_result_from_response(
+            201,
+            Some("https://example.com/1".to_string()),
+            "",
+            PostStatus::Published,
+        )
+        .unwrap();
+        assert_eq!(result.url, "https://example.com/1");
+        assert_eq!(result.preview, None);
+        assert_eq!(result.edit, None);
+        assert_eq!(result.creation_status, CreationStatus::Created);
+    }
+
+    #[test]
+    fn test_post_result_accepted_status() {
+        let result = post_result_from_response(
+            202,
+            Some("https://example.com/1".to_string()),
+            "",
+            PostStatus::Draft,
+        )
+        .unwrap();
+        assert_eq!(result.creation_status, CreationStatus::Accepted);
+    }
+
+    #[test]
+    fn test_post_result_from_microblog_json_body() {
+        let body = r#"{"url":"https://example.com/1","preview":"https://example.com/p","edit":"https://example.com/e"}"#;
+        let result = post_result_from_response(202, None, body, PostStatus::Published).unwrap();
+        assert_eq!(result.url, "https://example.com/1");
+        assert_eq!(result.preview, Some("https://example.com/p".to_string()));
+        assert_eq!(result.edit, Some("https://example.com/e".to_string()));
+    }
+
+    #[test]
+    fn test_post_result_prefers_location_header_over_body() {
+        let body = r#"{"url":"https://example.com/body","preview":"https://example.com/p"}"#;
+        let result = post_result_from_response(
+            201,
+            Some("https://example.com/header".to_string()),
+            body,
+            PostStatus::Published,
+        )
+        .unwrap();
+        assert_eq!(result.url, "https://example.com/header");
+        assert_eq!(result.preview, Some("https://example.com/p".to_string()));
+        assert_eq!(result.edit, None);
+    }
+
+    #[test]
+    fn test_post_result_without_url_is_an_error() {
+        let result = post_result_from_response(201, None, "", PostStatus::Published);
+        assert!(matches!(result, Err(PostError::MissingLocation)));
+    }
+
+    #[tokio::test]
+    async fn test_publish_sends_idempotency_key() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let api_url = format!(
+            "http://127.0.0.1:{}/micropub",
+            listener.local_addr().unwrap().port()
+        );
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let length = stream.read(&mut request).await.unwrap();
+            let response = "HTTP/1.1 201 Created\r\nLocation: https://example.com/1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request[..length]).to_lowercase()
+        });
+
+        let service = MicropubService::new(api_url, "token".to_string());
+        let post = Post::from_body("Hello".to_string(), PostStatus::Published);
+        let result = publish_post_with_idempotency_key(post, &service, "key-1")
+            .await
+            .unwrap();
+
+        assert_eq!(result.url, "https://example.com/1");
+        assert!(server.await.unwrap().contains("idempotency-key: key-1\r\n"));
+    }
 }
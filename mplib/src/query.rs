@@ -0,0 +1,137 @@
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::http::Retry;
+use crate::micropub_client::MicropubClient;
+use crate::micropub_service::MicropubService;
+use crate::post_error::PostError;
+use crate::server_config::{ServerConfig, SyndicationTarget};
+use crate::transport::HttpRequest;
+
+/// Query a Micropub service for its configuration and capabilities (`q=config`).
+pub async fn query_config(service: &MicropubService) -> Result<ServerConfig, PostError> {
+    MicropubClient::new(service.clone())?.query_config().await
+}
+
+/// Query a Micropub service for the categories used on existing posts (`q=category`),
+/// optionally limited to those matching a search term.
+pub async fn query_categories(
+    service: &MicropubService,
+    search: Option<&str>,
+) -> Result<Vec<String>, PostError> {
+    MicropubClient::new(service.clone())?
+        .query_categories(search)
+        .await
+}
+
+/// Query a Micropub service for the targets posts can be syndicated to (`q=syndicate-to`).
+pub async fn query_syndication_targets(
+    service: &MicropubService,
+) -> Result<Vec<SyndicationTarget>, PostError> {
+    MicropubClient::new(service.clone())?
+        .query_syndication_targets()
+        .await
+}
+
+impl MicropubClient {
+    /// Query the service for its configuration and capabilities (`q=config`).
+    pub async fn query_config(&self) -> Result<ServerConfig, PostError> {
+        self.query("config", &[]).await
+    }
+
+    /// Query the service for the categories used on existing posts (`q=category`), optionally
+    /// limited to those matching a search term.
+    pub async fn query_categories(&self, search: Option<&str>) -> Result<Vec<String>, PostError> {
+        let params: Vec<(&str, &str)> = search.map(|s| ("search", s)).into_iter().collect();
+        let response: CategoriesResponse = self.query("category", &params).await?;
+        Ok(response.categories)
+    }
+
+    /// Query the service for the targets posts can be syndicated to (`q=syndicate-to`).
+    pub async fn query_syndication_targets(&self) -> Result<Vec<SyndicationTarget>, PostError> {
+        let response: SyndicationTargetsResponse = self.query("syndicate-to", &[]).await?;
+        Ok(response.syndicate_to)
+    }
+
+    /// Send a `q=...` GET query with optional extra parameters and parse the JSON response.
+    ///
+    /// This can be used for queries this crate has no dedicated method for, such as `q=source`.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, PostError> {
+        let service = self.service();
+        let mut request = HttpRequest::get(&service.api_url)
+            .with_bearer_auth(&service.auth_token)
+            .with_query("q", query);
+        for (name, value) in params {
+            request = request.with_query(name, value);
+        }
+        let response = self.send(Retry::Idempotent, &request).await?;
+
+        Ok(serde_json::from_str(&response.body)?)
+    }
+}
+
+/// Represents the response to a `q=category` query.
+#[derive(Deserialize)]
+struct CategoriesResponse {
+    #[serde(default)]
+    categories: Vec<String>,
+}
+
+/// Represents the response to a `q=syndicate-to` query.
+#[derive(Deserialize)]
+struct SyndicationTargetsResponse {
+    #[serde(rename = "syndicate-to", default)]
+    syndicate_to: Vec<SyndicationTarget>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_transport::MockTransport;
+    use crate::transport::HttpResponse;
+
+    fn mock_client(transport: &MockTransport) -> MicropubClient {
+        let service = MicropubService::new(
+            "https://example.com/micropub".to_string(),
+            "token".to_string(),
+        );
+        MicropubClient::with_transport(service, transport.clone())
+    }
+
+    #[tokio::test]
+    async fn test_query_categories_sends_search_parameter() {
+        let transport = MockTransport::new();
+        transport.respond_with(HttpResponse::new(
+            200,
+            r#"{"categories":["rust","rustaceans"]}"#,
+        ));
+
+        let categories = mock_client(&transport)
+            .query_categories(Some("rust"))
+            .await
+            .unwrap();
+        assert_eq!(categories, vec!["rust", "rustaceans"]);
+
+        let query = &transport.requests()[0].query;
+        assert_eq!(
+            query,
+            &vec![
+                ("q".to_string(), "category".to_string()),
+                ("search".to_string(), "rust".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_with_malformed_json_is_an_error() {
+        let transport = MockTransport::new();
+        transport.respond_with(HttpResponse::new(200, "<html>Not JSON</html>"));
+
+        let result = mock_client(&transport).query_config().await;
+        assert!(matches!(result, Err(PostError::Json(_))));
+    }
+}
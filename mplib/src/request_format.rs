@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::properties::Properties;
+use crate::transport::HttpBody;
+
+/// Represents the syntax used to send create requests to a Micropub endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestFormat {
+    /// `application/x-www-form-urlencoded` with `h=entry`.
+    #[default]
+    Form,
+    /// `application/json` with `{"type": ["h-entry"], "properties": {...}}`.
+    Json,
+}
+
+impl RequestFormat {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RequestFormat::Form => "form",
+            RequestFormat::Json => "json",
+        }
+    }
+
+    /// Encode the properties of an h-entry as a create request body.
+    pub(crate) fn encode_entry(&self, properties: &Properties) -> HttpBody {
+        match self {
+            RequestFormat::Form => HttpBody::Form(properties.to_form("entry")),
+            RequestFormat::Json => HttpBody::Json(properties.to_json("entry")),
+        }
+    }
+
+    /// Encode an action on an existing post (e.g., `delete`) as a request body.
+    pub(crate) fn encode_action(&self, action: &str, url: &str) -> HttpBody {
+        match self {
+            RequestFormat::Form => HttpBody::Form(vec![
+                ("action".to_string(), action.to_string()),
+                ("url".to_string(), url.to_string()),
+            ]),
+            RequestFormat::Json => HttpBody::Json(json!({ "action": action, "url": url })),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+/// Represents the capabilities a Micropub endpoint reports in response to a `q=config` query.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// The URL of the media endpoint, if the server has one.
+    #[serde(
+        rename = "media-endpoint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub media_endpoint: Option<String>,
+    /// The syndication targets posts can be cross-posted to.
+    #[serde(rename = "syndicate-to", default)]
+    pub syndicate_to: Vec<SyndicationTarget>,
+    /// The kinds of posts the server supports.
+    #[serde(rename = "post-types", default)]
+    pub post_types: Vec<PostType>,
+    /// The blogs posts can be published to (e.g., Micro.blog's `mp-destination`).
+    #[serde(rename = "destination", default)]
+    pub destinations: Vec<Destination>,
+    /// The queries the server supports, such as `config`, `source`, or `category`.
+    #[serde(rename = "q", default)]
+    pub queries: Vec<String>,
+}
+
+/// Represents a syndication target advertised by a Micropub endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyndicationTarget {
+    pub uid: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Represents a destination (blog) advertised by a Micropub endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Destination {
+    pub uid: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Represents a post type advertised by a Micropub endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostType {
+    #[serde(rename = "type")]
+    pub post_type: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+impl ServerConfig {
+    /// Check if the server reports support for the given query (e.g., `category`).
+    pub fn supports_query(&self, query: &str) -> bool {
+        self.queries.iter().any(|q| q == query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_full_config() {
+        let json = r#"{
+            "media-endpoint": "https://example.com/micropub/media",
+            "syndicate-to": [{"uid": "https://mastodon.example/@me", "name": "Mastodon"}],
+            "post-types": [{"type": "note", "name": "Note"}],
+            "destination": [{"uid": "https://blog.example.com/", "name": "Blog", "microblog-audio": false}],
+            "q": ["config", "source", "category"]
+        }"#;
+        let config: ServerConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            config.media_endpoint.as_deref(),
+            Some("https://example.com/micropub/media")
+        );
+        assert_eq!(config.syndicate_to[0].name, "Mastodon");
+        assert_eq!(config.post_types[0].post_type, "note");
+        assert_eq!(config.destinations[0].uid, "https://blog.example.com/");
+        assert!(config.supports_query("category"));
+        assert!(!config.supports_query("syndicate-to"));
+    }
+
+    #[test]
+    fn test_deserialize_empty_config() {
+        let config: ServerConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config, ServerConfig::default());
+    }
+}
@@ -0,0 +1,84 @@
+use serde::Deserialize;
+
+/// Represents what a token endpoint reports about an access token.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TokenInfo {
+    /// The URL of the user the token belongs to.
+    #[serde(default)]
+    pub me: Option<String>,
+    /// The client the token was issued to.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// The scopes granted to the token, or None if the server did not report them.
+    #[serde(default, rename = "scope", deserialize_with = "deserialize_scopes")]
+    pub scopes: Option<Vec<String>>,
+}
+
+impl TokenInfo {
+    /// Check if the token was granted a scope. The legacy `post` scope grants `create`.
+    /// Returns true if the server did not report the scopes, since they cannot be checked.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes
+                .iter()
+                .any(|granted| granted == scope || (scope == "create" && granted == "post")),
+            None => true,
+        }
+    }
+
+    /// Get the scopes among `wanted` that were not granted to the token.
+    pub fn missing_scopes<'a>(&self, wanted: &[&'a str]) -> Vec<&'a str> {
+        wanted
+            .iter()
+            .copied()
+            .filter(|scope| !self.has_scope(scope))
+            .collect()
+    }
+}
+
+/// Deserialize the space-separated `scope` of a token endpoint response into a list.
+fn deserialize_scopes<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let scope: Option<String> = Option::deserialize(deserializer)?;
+    Ok(scope.map(|scope| scope.split_whitespace().map(str::to_string).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_token_info() {
+        let json = r#"{"me":"https://example.com/","client_id":"https://app.example/","scope":"create media"}"#;
+        let token_info: TokenInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(token_info.me.as_deref(), Some("https://example.com/"));
+        assert_eq!(
+            token_info.client_id.as_deref(),
+            Some("https://app.example/")
+        );
+        assert_eq!(
+            token_info.scopes,
+            Some(vec!["create".to_string(), "media".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_missing_scopes() {
+        let token_info = TokenInfo {
+            scopes: Some(vec!["post".to_string(), "media".to_string()]),
+            ..TokenInfo::default()
+        };
+        assert_eq!(
+            token_info.missing_scopes(&["create", "update", "delete", "media"]),
+            vec!["update", "delete"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_scopes_are_not_reported_missing() {
+        let token_info = TokenInfo::default();
+        assert!(token_info.missing_scopes(&["create"]).is_empty());
+    }
+}
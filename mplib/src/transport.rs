@@ -0,0 +1,241 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use reqwest::multipart::{Form, Part};
+use serde_json::Value;
+
+use crate::post_error::PostError;
+
+/// The future returned by [`Transport::send`].
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<HttpResponse, PostError>> + Send + 'a>>;
+
+/// Sends HTTP requests on behalf of a [`crate::MicropubClient`].
+///
+/// The client builds every request and interprets every response itself, so a transport only
+/// has to deliver requests and return the responses as they were received. Requests are sent
+/// with [`ReqwestTransport`] by default. Other transports can be used to send requests with
+/// another HTTP library, or to test code that publishes posts without a server (see
+/// `MockTransport`, available with the `testing` feature).
+///
+/// A transport reports failures to deliver a request as [`PostError::ConnectFailed`] if the
+/// request was certainly not sent, and as [`PostError::ConnectionLost`] otherwise, so that the
+/// client knows whether the request can be sent again.
+pub trait Transport: Send + Sync {
+    /// Send a request and return its response, whatever its status.
+    fn send<'a>(&'a self, request: &'a HttpRequest) -> TransportFuture<'a>;
+}
+
+/// The HTTP methods used by Micropub requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+/// Represents the body of an HTTP request.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum HttpBody {
+    #[default]
+    Empty,
+    /// Parameters encoded as `application/x-www-form-urlencoded`.
+    Form(Vec<(String, String)>),
+    /// A JSON document.
+    Json(Value),
+    /// A file sent as the single part of a `multipart/form-data` body.
+    Multipart {
+        field: String,
+        file_name: String,
+        content_type: String,
+        bytes: Vec<u8>,
+    },
+}
+
+/// Represents an HTTP request to be sent by a [`Transport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    /// Parameters appended to the query string of the URL.
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    /// The token sent in the `Authorization` header, if any.
+    pub bearer_token: Option<String>,
+    pub body: HttpBody,
+}
+
+impl HttpRequest {
+    /// Create a request with the given method and URL, and no body.
+    pub fn new(method: HttpMethod, url: &str) -> Self {
+        HttpRequest {
+            method,
+            url: url.to_string(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            bearer_token: None,
+            body: HttpBody::Empty,
+        }
+    }
+
+    /// Create a GET request for the given URL.
+    pub fn get(url: &str) -> Self {
+        HttpRequest::new(HttpMethod::Get, url)
+    }
+
+    /// Create a POST request for the given URL.
+    pub fn post(url: &str) -> Self {
+        HttpRequest::new(HttpMethod::Post, url)
+    }
+
+    /// Add a parameter to the query string of the URL.
+    pub fn with_query(mut self, name: &str, value: &str) -> Self {
+        self.query.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add a header to the request.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Authenticate the request with a bearer token.
+    pub fn with_bearer_auth(mut self, token: &str) -> Self {
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    /// Set the body of the request.
+    pub fn with_body(mut self, body: HttpBody) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Get the value of a header of the request, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// Represents the response to an HTTP request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// The URL of the response, which differs from the URL of the request if it was redirected.
+    /// A transport may leave it empty if it is the URL of the request.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// Create a response with the given status and body, and no headers.
+    pub fn new(status: u16, body: &str) -> Self {
+        HttpResponse {
+            status,
+            url: String::new(),
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    /// Add a header to the response.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Get the value of the first header with the given name, ignoring the case of the name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Get the values of all the headers with the given name, ignoring the case of the name.
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.headers
+            .iter()
+            .filter(move |(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Check if the status of the response is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Sends requests with a [`reqwest::Client`]. This is the transport used by default.
+#[derive(Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Create a transport that sends requests with the given client.
+    pub fn new(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send<'a>(&'a self, request: &'a HttpRequest) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let method = match request.method {
+                HttpMethod::Get => reqwest::Method::GET,
+                HttpMethod::Post => reqwest::Method::POST,
+            };
+            let mut builder = self.client.request(method, &request.url);
+            if !request.query.is_empty() {
+                builder = builder.query(&request.query);
+            }
+            for (name, value) in &request.headers {
+                builder = builder.header(name, value);
+            }
+            if let Some(token) = &request.bearer_token {
+                builder = builder.bearer_auth(token);
+            }
+            builder = match &request.body {
+                HttpBody::Empty => builder,
+                HttpBody::Form(params) => builder.form(params),
+                HttpBody::Json(body) => builder.json(body),
+                HttpBody::Multipart {
+                    field,
+                    file_name,
+                    content_type,
+                    bytes,
+                } => {
+                    let part = Part::bytes(bytes.clone())
+                        .file_name(file_name.clone())
+                        .mime_str(content_type)?;
+                    builder.multipart(Form::new().part(field.clone(), part))
+                }
+            };
+
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let url = response.url().to_string();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            let body = response.text().await?;
+
+            Ok(HttpResponse {
+                status,
+                url,
+                headers,
+                body,
+            })
+        })
+    }
+}
@@ -0,0 +1,173 @@
+use serde_json::{Map, Value, json};
+
+use crate::http::{Retry, location_header};
+use crate::micropub_client::MicropubClient;
+use crate::micropub_service::MicropubService;
+use crate::post_error::PostError;
+use crate::properties::Properties;
+use crate::transport::{HttpBody, HttpRequest};
+
+/// Represents a request to update the properties of an existing post.
+///
+/// Updates are always sent using the Micropub JSON syntax, regardless of the request format
+/// configured for the service, because form encoding cannot express them.
+pub struct PostUpdate {
+    /// The URL of the post to update.
+    pub url: String,
+    /// Properties whose values are replaced entirely.
+    pub replace: Properties,
+    /// Values added to properties, keeping any existing values.
+    pub add: Properties,
+    /// Individual values removed from properties.
+    pub delete_values: Properties,
+    /// Properties removed entirely.
+    pub delete_properties: Vec<String>,
+}
+
+impl PostUpdate {
+    /// Create an update for the post at the given URL that does not change anything yet.
+    pub fn new(url: String) -> Self {
+        PostUpdate {
+            url,
+            replace: Properties::new(),
+            add: Properties::new(),
+            delete_values: Properties::new(),
+            delete_properties: Vec::new(),
+        }
+    }
+
+    /// Check if the update contains no operations.
+    pub fn is_empty(&self) -> bool {
+        self.replace.is_empty()
+            && self.add.is_empty()
+            && self.delete_values.is_empty()
+            && self.delete_properties.is_empty()
+    }
+
+    /// Encode the update as a Micropub JSON update request.
+    fn to_json(&self) -> Result<Value, PostError> {
+        if !self.delete_values.is_empty() && !self.delete_properties.is_empty() {
+            return Err(PostError::InvalidInput(
+                "Deleting whole properties and individual values cannot be combined in one update"
+                    .to_string(),
+            ));
+        }
+
+        let mut body = Map::new();
+        body.insert("action".to_string(), json!("update"));
+        body.insert("url".to_string(), json!(self.url));
+
+        if !self.replace.is_empty() {
+            body.insert(
+                "replace".to_string(),
+                Value::Object(self.replace.to_json_map()),
+            );
+        }
+        if !self.add.is_empty() {
+            body.insert("add".to_string(), Value::Object(self.add.to_json_map()));
+        }
+        if !self.delete_values.is_empty() {
+            body.insert(
+                "delete".to_string(),
+                Value::Object(self.delete_values.to_json_map()),
+            );
+        } else if !self.delete_properties.is_empty() {
+            body.insert("delete".to_string(), json!(self.delete_properties));
+        }
+
+        Ok(Value::Object(body))
+    }
+}
+
+/// Update an existing post via a Micropub service.
+///
+/// Returns the URL of the post, which is the new URL if the server moved the post as a result
+/// of the update.
+pub async fn update_post(
+    update: PostUpdate,
+    service: &MicropubService,
+) -> Result<String, PostError> {
+    MicropubClient::new(service.clone())?.update(update).await
+}
+
+impl MicropubClient {
+    /// Update an existing post, and return its URL (see [`update_post`]).
+    pub async fn update(&self, update: PostUpdate) -> Result<String, PostError> {
+        if update.is_empty() {
+            return Err(PostError::InvalidInput(
+                "Update must change at least one property".to_string(),
+            ));
+        }
+
+        let service = self.service();
+        let request = HttpRequest::post(&service.api_url)
+            .with_bearer_auth(&service.auth_token)
+            .with_body(HttpBody::Json(update.to_json()?));
+        // Replacing or deleting values twice has the same effect as doing it once, but adding
+        // values twice would add them twice.
+        let retry = if update.add.is_empty() {
+            Retry::Idempotent
+        } else {
+            Retry::UnlessSent
+        };
+        let response = self.send(retry, &request).await?;
+
+        Ok(location_header(&response).unwrap_or(update.url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json_replace_add_and_delete_values() {
+        let mut update = PostUpdate::new("https://example.com/1".to_string());
+        update.replace.push_text("name", "New title");
+        update.add.push_text("category", "rust");
+        update.delete_values.push_text("category", "typo");
+
+        assert_eq!(
+            update.to_json().unwrap(),
+            json!({
+                "action": "update",
+                "url": "https://example.com/1",
+                "replace": { "name": ["New title"] },
+                "add": { "category": ["rust"] },
+                "delete": { "category": ["typo"] },
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_json_delete_properties() {
+        let mut update = PostUpdate::new("https://example.com/1".to_string());
+        update.delete_properties.push("name".to_string());
+
+        assert_eq!(
+            update.to_json().unwrap(),
+            json!({
+                "action": "update",
+                "url": "https://example.com/1",
+                "delete": ["name"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_json_rejects_mixed_deletes() {
+        let mut update = PostUpdate::new("https://example.com/1".to_string());
+        update.delete_properties.push("name".to_string());
+        update.delete_values.push_text("category", "typo");
+
+        assert!(matches!(update.to_json(), Err(PostError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let mut update = PostUpdate::new("https://example.com/1".to_string());
+        assert!(update.is_empty());
+        update.add.push_text("category", "rust");
+        assert!(!update.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use crate::http::Retry;
+use crate::micropub_client::MicropubClient;
+use crate::micropub_service::MicropubService;
+use crate::post_error::PostError;
+use crate::token_info::TokenInfo;
+use crate::transport::HttpRequest;
+
+/// Verify a service's access token and report what it grants.
+///
+/// The token is verified against the service's token endpoint if it has one, which reports the
+/// user, client, and scopes of the token. Otherwise, an authenticated `q=config` query verifies
+/// that the Micropub endpoint accepts the token, and nothing more is known about it.
+pub async fn verify_token(service: &MicropubService) -> Result<TokenInfo, PostError> {
+    MicropubClient::new(service.clone())?.verify_token().await
+}
+
+impl MicropubClient {
+    /// Verify the service's access token and report what it grants (see [`verify_token`]).
+    pub async fn verify_token(&self) -> Result<TokenInfo, PostError> {
+        let service = self.service();
+        let Some(token_endpoint) = &service.token_endpoint else {
+            self.query_config().await?;
+            return Ok(TokenInfo::default());
+        };
+
+        let request = HttpRequest::get(token_endpoint)
+            .with_bearer_auth(&service.auth_token)
+            .with_header("Accept", "application/json");
+        let response = self.send(Retry::Idempotent, &request).await?;
+
+        Ok(serde_json::from_str(&response.body)?)
+    }
+}
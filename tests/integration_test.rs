@@ -1,20 +1,24 @@
-use mp::{MicropubService, PostStatus};
+use mp_testserver::{ResponseStyle, RunningTestServer, TestServer};
+use mplib::{MicropubService, PostStatus};
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use tokio::io::AsyncWriteExt;
 
 #[test]
 fn test_library_imports() {
     // Test that we can create the types that should be exported
     let service = MicropubService::new(
         "https://example.com/micropub".to_string(),
-        "test-token".to_string()
+        "test-token".to_string(),
     );
-    
+
     assert_eq!(service.api_url, "https://example.com/micropub");
     assert_eq!(service.auth_token, "test-token");
-    
+
     // Test that PostStatus can be created and used
     let status = PostStatus::Published;
     assert_eq!(status.as_str(), "published");
-    
+
     let draft_status = PostStatus::Draft;
     assert_eq!(draft_status.as_str(), "draft");
 }
@@ -23,9 +27,9 @@ fn test_library_imports() {
 fn test_microblog_service_from_args() {
     let result = MicropubService::from_args(
         "https://test.com/micropub".to_string(),
-        "secret-token".to_string()
+        "secret-token".to_string(),
     );
-    
+
     assert!(result.is_ok());
     let service = result.unwrap();
     assert_eq!(service.api_url, "https://test.com/micropub");
@@ -34,17 +38,327 @@ fn test_microblog_service_from_args() {
 
 #[test]
 fn test_microblog_service_from_args_empty() {
-    let result = MicropubService::from_args(
-        "".to_string(),
-        "token".to_string()
-    );
-    
+    let result = MicropubService::from_args("".to_string(), "token".to_string());
+
     assert!(result.is_err());
-    
-    let result2 = MicropubService::from_args(
-        "https://test.com".to_string(),
-        "".to_string()
-    );
-    
+
+    let result2 = MicropubService::from_args("https://test.com".to_string(), "".to_string());
+
     assert!(result2.is_err());
 }
+
+/// A folder holding the configuration and data of `mp` for one test, removed when dropped.
+struct TestHome {
+    dir: PathBuf,
+}
+
+impl TestHome {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("mp-it-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TestHome { dir }
+    }
+
+    /// Create a command running `mp` with the given arguments in this home.
+    fn command(&self, args: &[&str]) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_mp"));
+        command
+            .args(args)
+            .env_clear()
+            .env("HOME", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_DATA_HOME", self.dir.join("data"))
+            .env("MP_RETRIES", "0");
+        command
+    }
+
+    /// Run `mp` with the given arguments against the server, authenticated with `token`.
+    async fn mp(&self, server: &RunningTestServer, token: &str, args: &[&str]) -> Output {
+        self.command(args)
+            .env("MP_API_URL", server.micropub_url())
+            .env("MP_AUTH_TOKEN", token)
+            .output()
+            .await
+            .unwrap()
+    }
+
+    /// Like `mp`, writing `input` to the standard input of `mp`.
+    async fn mp_with_stdin(
+        &self,
+        server: &RunningTestServer,
+        token: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Output {
+        let mut child = self
+            .command(args)
+            .env("MP_API_URL", server.micropub_url())
+            .env("MP_AUTH_TOKEN", token)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(input.as_bytes()).await.unwrap();
+        drop(stdin);
+        child.wait_with_output().await.unwrap()
+    }
+
+    /// Run `mp` with the given arguments, using only its configuration file.
+    async fn mp_configured(&self, args: &[&str]) -> Output {
+        self.command(args).output().await.unwrap()
+    }
+
+    /// Get a setting of a profile with `mp config get`.
+    async fn setting(&self, profile: &str, key: &str) -> String {
+        let output = self
+            .mp_configured(&["config", "get", key, "--profile", profile])
+            .await;
+        assert!(output.status.success(), "{:?}", output);
+        stdout(&output).trim().to_string()
+    }
+}
+
+impl Drop for TestHome {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[tokio::test]
+async fn test_mp_post_publishes_post() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("post");
+
+    let output = home
+        .mp(
+            &server,
+            "token",
+            &[
+                "post",
+                "Hello from mp",
+                "--title",
+                "Hello",
+                "-c",
+                "rust",
+                "-c",
+                "cli",
+            ],
+        )
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+
+    let posts = server.posts();
+    assert_eq!(posts.len(), 1);
+    assert!(stdout(&output).contains(&posts[0].url));
+    assert_eq!(posts[0].text("content"), Some("Hello from mp"));
+    assert_eq!(posts[0].text("name"), Some("Hello"));
+    assert_eq!(posts[0].text("post-status"), Some("published"));
+    assert_eq!(posts[0].values("category").len(), 2);
+}
+
+#[tokio::test]
+async fn test_mp_draft_creates_draft() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("draft");
+
+    let output = home.mp(&server, "token", &["draft", "Not ready yet"]).await;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout(&output).starts_with("Draft created successfully."));
+
+    let posts = server.posts();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].text("post-status"), Some("draft"));
+}
+
+#[tokio::test]
+async fn test_mp_post_with_micro_blog_responses() {
+    let server = TestServer::new("token")
+        .with_response_style(ResponseStyle::MicroBlog)
+        .start()
+        .await
+        .unwrap();
+    let home = TestHome::new("microblog");
+
+    let output = home
+        .mp(&server, "token", &["post", "Hello, Micro.blog"])
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = stdout(&output);
+    assert!(stdout.contains(&format!("URL:     {}", server.posts()[0].url)));
+    assert!(stdout.contains("Preview: "));
+    assert!(stdout.contains("Edit:    "));
+}
+
+#[tokio::test]
+async fn test_mp_post_uploads_photo() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("photo");
+    let photo = home.dir.join("photo.jpg");
+    std::fs::write(&photo, b"not really a jpeg").unwrap();
+
+    let output = home
+        .mp(
+            &server,
+            "token",
+            &["post", "With a photo", "--photo", photo.to_str().unwrap()],
+        )
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+
+    let media = server.media();
+    assert_eq!(media.len(), 1);
+    assert_eq!(media[0].content_type, "image/jpeg");
+    assert_eq!(media[0].bytes, b"not really a jpeg");
+    assert_eq!(server.posts()[0].text("photo"), Some(media[0].url.as_str()));
+}
+
+#[tokio::test]
+async fn test_mp_post_with_wrong_token_fails() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("unauthorized");
+
+    let output = home.mp(&server, "wrong", &["post", "Hello"]).await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unauthorized"));
+    assert!(server.posts().is_empty());
+}
+
+#[tokio::test]
+async fn test_mp_post_sends_stdin_starting_with_dashes_unchanged() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("stdin");
+
+    // Only files and composed text may have front matter.
+    let content = "---\nA horizontal rule, then more text.\n---\n";
+    let output = home
+        .mp_with_stdin(&server, "token", &["post", "--stdin"], content)
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+
+    let posts = server.posts();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].text("content"), Some(content));
+    assert_eq!(posts[0].text("name"), None);
+}
+
+#[tokio::test]
+async fn test_mp_post_html_requires_json_format() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("html");
+
+    let output = home
+        .mp(&server, "token", &["post", "--html", "<p>Hello</p>"])
+        .await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("JSON request format"));
+    assert!(server.posts().is_empty());
+}
+
+#[tokio::test]
+async fn test_mp_configure_non_interactive_keeps_unchanged_settings() {
+    let server = TestServer::new("token").start().await.unwrap();
+    let home = TestHome::new("configure");
+
+    let output = home
+        .mp(
+            &server,
+            "token",
+            &[
+                "configure",
+                "--non-interactive",
+                "--profile",
+                "blog",
+                "--request-format",
+                "json",
+                "--quiet",
+                "y",
+                "--extract-title",
+                "y",
+                "--syndicate-to",
+                "mastodon",
+            ],
+        )
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+    for (key, value) in [
+        ("default_behavior.timeout", "30"),
+        ("default_behavior.compose_in_editor", "true"),
+        ("token_env", "MP_BLOG_TOKEN"),
+    ] {
+        let output = home
+            .mp_configured(&["config", "set", key, value, "--profile", "blog"])
+            .await;
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    // Neither the API URL nor the token is given: the profile's are kept.
+    let output = home
+        .mp_configured(&[
+            "configure",
+            "--non-interactive",
+            "--profile",
+            "blog",
+            "--quiet",
+            "n",
+        ])
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(
+        home.setting("blog", "service.api_url").await,
+        server.micropub_url()
+    );
+    assert_eq!(home.setting("blog", "service.request_format").await, "json");
+    assert_eq!(
+        home.setting("blog", "default_behavior.quiet").await,
+        "false"
+    );
+    assert_eq!(
+        home.setting("blog", "default_behavior.extract_title").await,
+        "true"
+    );
+    assert_eq!(
+        home.setting("blog", "default_behavior.syndicate_to").await,
+        "mastodon"
+    );
+    assert_eq!(home.setting("blog", "default_behavior.timeout").await, "30");
+    assert_eq!(
+        home.setting("blog", "default_behavior.compose_in_editor")
+            .await,
+        "true"
+    );
+    assert_eq!(home.setting("blog", "token_env").await, "MP_BLOG_TOKEN");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_mp_config_upgrade_backup_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = TestHome::new("upgrade");
+    let config_dir = home.dir.join("config").join("mp");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    let config_path = config_dir.join("config.toml");
+    std::fs::write(
+        &config_path,
+        "[service]\napi_url = \"https://example.com/micropub\"\nauth_token = \"secret\"\n\n[default_behavior]\nquiet = false\nextract_title = false\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let output = home
+        .mp_configured(&["config", "set", "default_behavior.quiet", "true"])
+        .await;
+    assert!(output.status.success(), "{:?}", output);
+
+    let backup = std::fs::metadata(config_dir.join("config.toml.v1.bak")).unwrap();
+    assert_eq!(backup.permissions().mode() & 0o777, 0o600);
+}